@@ -0,0 +1,141 @@
+//! Compares emit throughput of `sync::EventBus` against a registry guarded by
+//! a plain lock held for the whole dispatch, while other threads keep
+//! registering listeners.
+//!
+//! Run with `cargo bench --bench contention`.
+
+use std::{
+    collections::HashMap,
+    hint::black_box,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use tram::{prelude::*, sync::EventBus};
+
+const EMITTERS: usize = 8;
+const EMITS_PER_THREAD: usize = 100_000;
+const INITIAL_LISTENERS: usize = 8;
+const LISTENER_WORK: usize = 16;
+const REGISTRATION_INTERVAL: Duration = Duration::from_micros(100);
+
+type PlainHandler = Box<dyn Fn(Option<&u64>) + Send + Sync>;
+
+/// A bus whose listener table is behind a mutex held for the whole dispatch
+#[derive(Default)]
+struct PlainBus {
+    listeners: Mutex<HashMap<u8, Vec<PlainHandler>>>,
+}
+
+impl PlainBus {
+    fn on<F>(&self, event: u8, f: F)
+    where
+        F: Fn(Option<&u64>) + Send + Sync + 'static,
+    {
+        let mut listeners = self.listeners.lock().unwrap();
+        listeners.entry(event).or_default().push(Box::new(f));
+    }
+
+    fn emit_with_value(&self, event: u8, value: Option<&u64>) {
+        let listeners = self.listeners.lock().unwrap();
+        if let Some(handlers) = listeners.get(&event) {
+            handlers.iter().for_each(|handler| handler(value));
+        }
+    }
+}
+
+/// Runs `EMITTERS` threads calling `emit` while one thread calls `register`
+/// every `REGISTRATION_INTERVAL`, returning the time taken by the emitters
+/// and the number of registrations performed meanwhile.
+fn run<B, Emit, Register>(bus: Arc<B>, emit: Emit, register: Register) -> (Duration, usize)
+where
+    B: Send + Sync + 'static,
+    Emit: Fn(&B, u64) + Send + Sync + Copy + 'static,
+    Register: Fn(&B) + Send + 'static,
+{
+    let done = Arc::new(AtomicBool::new(false));
+    let registrations = Arc::new(AtomicUsize::new(0));
+
+    let registrar = {
+        let bus = Arc::clone(&bus);
+        let done = Arc::clone(&done);
+        let registrations = Arc::clone(&registrations);
+        thread::spawn(move || {
+            while !done.load(Ordering::Relaxed) {
+                register(&bus);
+                registrations.fetch_add(1, Ordering::Relaxed);
+                thread::sleep(REGISTRATION_INTERVAL);
+            }
+        })
+    };
+
+    let start = Instant::now();
+    let emitters = (0..EMITTERS)
+        .map(|_| {
+            let bus = Arc::clone(&bus);
+            thread::spawn(move || {
+                for i in 0..EMITS_PER_THREAD as u64 {
+                    emit(&bus, i);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    emitters.into_iter().for_each(|t| t.join().unwrap());
+    let elapsed = start.elapsed();
+
+    done.store(true, Ordering::Relaxed);
+    registrar.join().unwrap();
+
+    (elapsed, registrations.load(Ordering::Relaxed))
+}
+
+fn report(name: &str, (elapsed, registrations): (Duration, usize)) {
+    let emits = (EMITTERS * EMITS_PER_THREAD) as f64;
+    println!(
+        "{:<14} {:>10.2?} {:>12.0} emits/s {:>8} registrations",
+        name,
+        elapsed,
+        emits / elapsed.as_secs_f64(),
+        registrations
+    );
+}
+
+/// A listener doing a small amount of work on the payload
+fn noop(value: Option<&u64>) {
+    let mut acc = value.copied().unwrap_or_default();
+    for _ in 0..LISTENER_WORK {
+        acc = black_box(acc.wrapping_mul(31).wrapping_add(7));
+    }
+    black_box(acc);
+}
+
+fn main() {
+    let cow: Arc<EventBus<u8, u64>> = Arc::new(EventBus::unbound());
+    for _ in 0..INITIAL_LISTENERS {
+        cow.on(0, |_, value| noop(value)).unwrap();
+    }
+    let cow_result = run(
+        cow,
+        |bus, i| bus.emit_with_value(0, Some(&i)).unwrap(),
+        |bus| {
+            bus.on(1, |_, value| noop(value)).unwrap();
+        },
+    );
+
+    let plain = Arc::new(PlainBus::default());
+    for _ in 0..INITIAL_LISTENERS {
+        plain.on(0, noop);
+    }
+    let plain_result = run(
+        plain,
+        |bus, i| bus.emit_with_value(0, Some(&i)),
+        |bus| bus.on(1, noop),
+    );
+
+    report("copy-on-write", cow_result);
+    report("plain lock", plain_result);
+}
@@ -5,6 +5,11 @@
 //! To re-emit events inside `on` closures do not clone the original bus, use the bus
 //! reference provided to the closure instead.
 //!
+//! Listeners can be added or removed from within `on` closures as well. On an `unsync` bus these
+//! changes are queued and applied once the outermost emit completes, so they never affect the event
+//! being dispatched nor events re-emitted while dispatching it. On a `sync` bus they apply to any emit
+//! started afterwards, while dispatches already in progress keep the listeners they started with.
+//!
 //! ## Example
 //!
 //! ```rust
@@ -121,9 +126,138 @@
 //! assert_eq!(*status.borrow(), Some(123));
 //! assert_eq!(bus.event_count(), 1);
 //! ```
+//!
+//! ## Removing listeners
+//!
+//! `on` returns a `SubscriptionId` that can later be passed to `off` to remove the listener.
+//!
+//! ```rust
+//! use tram::{prelude::*, unsync::EventBus};
+//! use std::{rc::Rc, cell::Cell};
+//!
+//! let bus: EventBus<&str, ()> = EventBus::unbound();
+//! let calls = Rc::new(Cell::new(0));
+//! let calls_closure = Rc::clone(&calls);
+//!
+//! let subscription = bus
+//!     .on("tick", move |_bus, _| calls_closure.set(calls_closure.get() + 1))
+//!     .unwrap();
+//!
+//! bus.emit("tick").expect("Failed to emit");
+//! bus.off(subscription).expect("Failed to remove listener");
+//! bus.emit("tick").expect("Failed to emit");
+//!
+//! assert_eq!(calls.get(), 1);
+//! ```
+//!
+//! Alternatively `subscribe` returns a `Subscription` guard that removes the listener when dropped,
+//! which makes it easy to tie a listener to the lifetime of the component that registered it.
+//!
+//! ```rust
+//! use tram::{prelude::*, unsync::{EventBus, Subscription}};
+//!
+//! struct Widget {
+//!     _on_resize: Subscription<&'static str, (u32, u32)>,
+//! }
+//!
+//! let bus: EventBus<&str, (u32, u32)> = EventBus::unbound();
+//! let widget = Widget {
+//!     _on_resize: bus.subscribe("resize", |_bus, _size| {}).unwrap(),
+//! };
+//!
+//! drop(widget);
+//! assert_eq!(bus.emit("resize"), Ok(()));
+//! ```
+//!
+//! ## Listening to every event
+//!
+//! `on_any` registers a listener that receives every event emitted on the bus, along with the event
+//! itself, which comes in handy for logging and auditing.
+//!
+//! ```rust
+//! use tram::{prelude::*, unsync::EventBus};
+//! use std::{rc::Rc, cell::RefCell};
+//!
+//! let bus: EventBus<&str, u8> = EventBus::unbound();
+//! let log = Rc::new(RefCell::new(Vec::new()));
+//! let log_closure = Rc::clone(&log);
+//!
+//! bus.on_any(move |_bus, event, value| {
+//!     log_closure.borrow_mut().push(format!("{} {:?}", event, value));
+//! })
+//! .unwrap();
+//!
+//! bus.emit_with_value("start", Some(&1)).expect("Failed to emit");
+//! bus.emit("stop").expect("Failed to emit");
+//!
+//! assert_eq!(*log.borrow(), vec!["start Some(1)", "stop None"]);
+//! ```
+//!
+//! ## Topics
+//!
+//! `TopicBus` keys events by dot-separated topics such as `order.created` and lets listeners
+//! subscribe with patterns: `*` matches exactly one segment and `#` matches zero or more.
+//!
+//! ```rust
+//! use tram::unsync::TopicBus;
+//! use std::{rc::Rc, cell::RefCell};
+//!
+//! let bus: TopicBus<()> = TopicBus::new();
+//! let log = Rc::new(RefCell::new(Vec::new()));
+//! let log_closure = Rc::clone(&log);
+//!
+//! bus.on("order.#", move |_bus, topic, _value| {
+//!     log_closure.borrow_mut().push(topic.to_owned());
+//! })
+//! .unwrap();
+//!
+//! bus.emit("order.created").expect("Failed to emit");
+//! bus.emit("order.eu.shipped").expect("Failed to emit");
+//! bus.emit("invoice.created").expect("Failed to emit");
+//!
+//! assert_eq!(*log.borrow(), vec!["order.created", "order.eu.shipped"]);
+//! ```
+//!
+//! ## Typed events
+//!
+//! `TypedBus` keys listeners by the type of the emitted event rather than by its value, so every
+//! event can carry its own payload and listeners receive it without unwrapping.
+//!
+//! ```rust
+//! use tram::unsync::TypedBus;
+//! use std::{rc::Rc, cell::Cell};
+//!
+//! struct Resized {
+//!     width: u32,
+//!     height: u32,
+//! }
+//!
+//! let bus = TypedBus::new();
+//! let area = Rc::new(Cell::new(0));
+//! let area_closure = Rc::clone(&area);
+//!
+//! bus.on(move |_bus, event: &Resized| {
+//!     area_closure.set(event.width * event.height);
+//! })
+//! .unwrap();
+//!
+//! bus.emit(Resized { width: 4, height: 3 }).expect("Failed to emit");
+//!
+//! assert_eq!(area.get(), 12);
+//! ```
+//!
+//! ## Typed variant payloads
+//!
+//! With the `derive` feature enabled, `#[derive(Event)]` on an enum whose variants carry either no
+//! data or a single field generates a field-less `Kind` enum to key the bus and a marker type for
+//! each variant, so that `on_event` listeners receive the payload of the variant they registered
+//! for (see the `Event` trait in `prelude`).
 
+pub mod limit;
+mod listener;
 pub mod prelude;
 pub mod sync;
+pub mod topic;
 pub mod unsync;
 
 
@@ -0,0 +1,601 @@
+//! Policies bounding the events a bus can emit.
+//!
+//! A bus is given its policy on construction with `EventBus::with_limit`,
+//! and asks it for permission before dispatching each event:
+//!
+//! ```rust
+//! use tram::{prelude::*, limit::Total, unsync::EventBus};
+//!
+//! let bus: EventBus<&str, ()> = EventBus::with_limit(Total::new(1));
+//!
+//! bus.emit("start").expect("Failed to emit");
+//! assert_eq!(bus.emit("start"), Err(Error::Disconnected { limit: 1, count: 1 }));
+//! assert!(bus.disconnected());
+//! ```
+//!
+//! Custom policies only need to implement [`EmitLimit`], and can refuse
+//! events with `Error::Rejected`. Those of a `sync` bus have to be `Send`
+//! and `Sync` as well.
+//!
+//! Policies can be combined in a pair, which only lets an event through if
+//! both of them do:
+//!
+//! ```rust
+//! use tram::{limit::{PerEvent, Total}, prelude::*, unsync::EventBus};
+//!
+//! let limit = (Total::new(10), PerEvent::new().limit("init", 1));
+//! let bus: EventBus<&str, ()> = EventBus::with_limit(limit);
+//!
+//! bus.emit("init").expect("Failed to emit");
+//! assert_eq!(bus.emit("init"), Err(Error::EventDisconnected { limit: 1, count: 1 }));
+//! bus.emit("tick").expect("Failed to emit");
+//! ```
+//!
+//! ## Rate limits
+//!
+//! [`RateLimit`] gives some events a token bucket, refilled over time, and
+//! lets them through as long as it has tokens left. What happens to the
+//! ones emitted past that is set with [`Overflow`]. Time is read from a
+//! [`Clock`], which tests can drive by hand with a [`ManualClock`]:
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tram::{limit::{ManualClock, Overflow, RateLimit}, prelude::*, unsync::EventBus};
+//!
+//! let clock = ManualClock::new();
+//! let limit = RateLimit::with_clock(clock.clone())
+//!     .limit("telemetry", 100, Duration::from_secs(1))
+//!     .overflow(Overflow::Drop);
+//! let bus: EventBus<&str, ()> = EventBus::with_limit(limit);
+//!
+//! for _ in 0..150 {
+//!     bus.emit("telemetry").expect("Failed to emit");
+//! }
+//! assert_eq!(bus.event_count(), 100);
+//!
+//! clock.advance(Duration::from_millis(100));
+//! for _ in 0..50 {
+//!     bus.emit("telemetry").expect("Failed to emit");
+//! }
+//! assert_eq!(bus.event_count(), 110);
+//! ```
+
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::prelude::Error;
+
+/// What a bus does with an event once its limit policy let it through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    /// The event is dispatched to its listeners
+    Emit,
+
+    /// The event is discarded, and the emit succeeds without dispatching it
+    Drop,
+}
+
+/// Decides whether a bus can emit an event
+pub trait EmitLimit<E> {
+    /// Reserves one emit of `event`, or returns the error the emit has to
+    /// fail with.
+    ///
+    /// Emits that are dropped rather than reserved are not given back with
+    /// `release`.
+    fn acquire(&self, event: &E) -> Result<Admission, Error>;
+
+    /// Gives back an emit of `event` reserved with `acquire` that did not
+    /// happen after all.
+    fn release(&self, _event: &E) {}
+
+    /// Whether the bus can't emit any event anymore
+    fn disconnected(&self) -> bool {
+        false
+    }
+
+    /// Whether the bus can't emit `event` anymore, while other events may
+    /// still be emitted
+    fn event_disconnected(&self, _event: &E) -> bool {
+        false
+    }
+
+    /// Forgets the emits reserved so far, as requested by `reset` on the
+    /// bus
+    fn reset(&self) {}
+}
+
+/// Lets a bus emit any number of events
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Unlimited;
+
+impl<E> EmitLimit<E> for Unlimited {
+    fn acquire(&self, _event: &E) -> Result<Admission, Error> {
+        Ok(Admission::Emit)
+    }
+}
+
+/// Lets a bus emit up to a number of events in total, failing with
+/// `Error::Disconnected` afterwards
+#[derive(Debug)]
+pub struct Total {
+    limit: usize,
+    count: AtomicUsize,
+}
+
+impl Total {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            count: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<E> EmitLimit<E> for Total {
+    fn acquire(&self, _event: &E) -> Result<Admission, Error> {
+        self.count
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                (count < self.limit).then_some(count + 1)
+            })
+            .map(|_| Admission::Emit)
+            .map_err(|count| Error::Disconnected {
+                limit: self.limit,
+                count,
+            })
+    }
+
+    fn release(&self, _event: &E) {
+        self.count.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    fn disconnected(&self) -> bool {
+        self.count.load(Ordering::SeqCst) >= self.limit
+    }
+
+    fn reset(&self) {
+        self.count.store(0, Ordering::SeqCst);
+    }
+}
+
+/// How many times an event can be emitted, and how many it has been
+#[derive(Debug)]
+struct EventLimit {
+    limit: usize,
+    count: AtomicUsize,
+}
+
+/// Lets a bus emit each of some events up to a number of times, failing
+/// with `Error::EventDisconnected` afterwards, while any other event keeps
+/// flowing.
+#[derive(Debug)]
+pub struct PerEvent<E> {
+    limits: HashMap<E, EventLimit>,
+}
+
+impl<E> PerEvent<E> {
+    pub fn new() -> Self {
+        Self {
+            limits: HashMap::new(),
+        }
+    }
+}
+
+impl<E> Default for PerEvent<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> PerEvent<E>
+where
+    E: Hash + Eq,
+{
+    /// Allows `event` to be emitted up to `limit` times
+    pub fn limit(mut self, event: E, limit: usize) -> Self {
+        let count = AtomicUsize::new(0);
+        self.limits.insert(event, EventLimit { limit, count });
+        self
+    }
+}
+
+impl<E> EmitLimit<E> for PerEvent<E>
+where
+    E: Hash + Eq,
+{
+    fn acquire(&self, event: &E) -> Result<Admission, Error> {
+        match self.limits.get(event) {
+            Some(EventLimit { limit, count }) => count
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                    (count < *limit).then_some(count + 1)
+                })
+                .map(|_| Admission::Emit)
+                .map_err(|count| Error::EventDisconnected {
+                    limit: *limit,
+                    count,
+                }),
+            None => Ok(Admission::Emit),
+        }
+    }
+
+    fn release(&self, event: &E) {
+        if let Some(EventLimit { count, .. }) = self.limits.get(event) {
+            count.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    fn event_disconnected(&self, event: &E) -> bool {
+        match self.limits.get(event) {
+            Some(EventLimit { limit, count }) => count.load(Ordering::SeqCst) >= *limit,
+            None => false,
+        }
+    }
+
+    fn reset(&self) {
+        for EventLimit { count, .. } in self.limits.values() {
+            count.store(0, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Lets a bus emit an event only if both policies do, asking them in order.
+///
+/// The emit reserved by the first policy is given back if the second one
+/// refuses or drops the event.
+impl<E, A, B> EmitLimit<E> for (A, B)
+where
+    A: EmitLimit<E>,
+    B: EmitLimit<E>,
+{
+    fn acquire(&self, event: &E) -> Result<Admission, Error> {
+        if self.0.acquire(event)? == Admission::Drop {
+            return Ok(Admission::Drop);
+        }
+
+        let admission = self
+            .1
+            .acquire(event)
+            .inspect_err(|_| self.0.release(event))?;
+        if admission == Admission::Drop {
+            self.0.release(event);
+        }
+        Ok(admission)
+    }
+
+    fn release(&self, event: &E) {
+        self.0.release(event);
+        self.1.release(event);
+    }
+
+    fn disconnected(&self) -> bool {
+        self.0.disconnected() || self.1.disconnected()
+    }
+
+    fn event_disconnected(&self, event: &E) -> bool {
+        self.0.event_disconnected(event) || self.1.event_disconnected(event)
+    }
+
+    fn reset(&self) {
+        self.0.reset();
+        self.1.reset();
+    }
+}
+
+/// Lets a bus emit up to a number of events within any span of time as
+/// long as `window`, failing with `Error::Throttled` in between.
+#[derive(Debug)]
+pub struct SlidingWindow<C = SystemClock> {
+    clock: C,
+    limit: usize,
+    window: Duration,
+    emits: Mutex<VecDeque<Instant>>,
+}
+
+impl SlidingWindow {
+    pub fn new(limit: usize, window: Duration) -> Self {
+        Self::with_clock(limit, window, SystemClock)
+    }
+}
+
+impl<C> SlidingWindow<C> {
+    /// Creates a sliding window reading the time from `clock`
+    pub fn with_clock(limit: usize, window: Duration, clock: C) -> Self {
+        Self {
+            clock,
+            limit,
+            window,
+            emits: Mutex::new(VecDeque::with_capacity(limit)),
+        }
+    }
+}
+
+impl<E, C> EmitLimit<E> for SlidingWindow<C>
+where
+    C: Clock,
+{
+    fn acquire(&self, _event: &E) -> Result<Admission, Error> {
+        let mut emits = self.emits.lock().map_err(|_| Error::Poisoned)?;
+        let now = self.clock.now();
+        while emits
+            .front()
+            .is_some_and(|emit| now.duration_since(*emit) >= self.window)
+        {
+            emits.pop_front();
+        }
+
+        if emits.len() >= self.limit {
+            return Err(Error::Throttled {
+                limit: self.limit,
+                window: self.window,
+            });
+        }
+
+        emits.push_back(now);
+        Ok(Admission::Emit)
+    }
+
+    fn release(&self, _event: &E) {
+        if let Ok(mut emits) = self.emits.lock() {
+            emits.pop_back();
+        }
+    }
+
+    fn reset(&self) {
+        if let Ok(mut emits) = self.emits.lock() {
+            emits.clear();
+        }
+    }
+}
+
+/// A source of time for the policies that depend on it
+pub trait Clock {
+    /// The current instant
+    fn now(&self) -> Instant;
+
+    /// Blocks until `duration` has passed
+    fn sleep(&self, duration: Duration);
+}
+
+/// The clock of the system
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// A clock that only moves forward when told to, or when something sleeps
+/// on it. Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Moves the time of this clock forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        if let Ok(mut now) = self.now.lock() {
+            *now += duration;
+        }
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self
+            .now
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+/// What a `RateLimit` does with the events emitted past their rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Discards them, without failing the emit
+    Drop,
+
+    /// Fails their emit with `Error::RateLimited`
+    #[default]
+    Error,
+
+    /// Waits on the clock until they fit within their rate, then emits them.
+    /// Events limited to no emits at all fail with `Error::RateLimited`.
+    Defer,
+}
+
+/// The token bucket of an event, kept as the instant it will be full
+/// again rather than as a count of tokens, so that it refills without any
+/// rounding.
+#[derive(Debug)]
+struct Bucket {
+    limit: usize,
+    per: Duration,
+
+    /// The time it takes to refill one token
+    interval: Duration,
+
+    /// When the bucket will be full again, if it is not full already
+    full_at: Instant,
+}
+
+impl Bucket {
+    fn new(limit: usize, per: Duration, now: Instant) -> Self {
+        let interval = match limit {
+            0 => Duration::ZERO,
+            limit => {
+                let nanos = per.as_nanos() / limit as u128;
+                Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+            }
+        };
+
+        Self {
+            limit,
+            per,
+            interval,
+            full_at: now,
+        }
+    }
+
+    /// Takes a token if one is left at `now`, or returns how long until one
+    /// is, if it ever will be.
+    fn take(&mut self, now: Instant) -> Result<(), Option<Duration>> {
+        if self.limit == 0 {
+            return Err(None);
+        }
+
+        // The bucket can be missing every token but one and still let an
+        // event through
+        let full_at = self.full_at.max(now);
+        let missing = full_at - now;
+        let tolerance = self
+            .interval
+            .saturating_mul(u32::try_from(self.limit - 1).unwrap_or(u32::MAX));
+        if missing > tolerance {
+            return Err(Some(missing - tolerance));
+        }
+
+        self.full_at = full_at + self.interval;
+        Ok(())
+    }
+
+    /// Puts back a token taken with `take`
+    fn put_back(&mut self) {
+        if let Some(full_at) = self.full_at.checked_sub(self.interval) {
+            self.full_at = full_at;
+        }
+    }
+}
+
+/// Lets each of some events be emitted up to a number of times per span of
+/// time through a token bucket, treating the emits past that as set with
+/// `overflow`. Any other event keeps flowing.
+///
+/// Buckets start full, so an event can be emitted as many times as its
+/// limit in a burst.
+#[derive(Debug)]
+pub struct RateLimit<E, C = SystemClock> {
+    clock: C,
+    overflow: Overflow,
+    buckets: HashMap<E, Mutex<Bucket>>,
+}
+
+impl<E> RateLimit<E> {
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock)
+    }
+}
+
+impl<E> Default for RateLimit<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E, C> RateLimit<E, C> {
+    /// Creates a rate limit reading the time from `clock`
+    pub fn with_clock(clock: C) -> Self {
+        Self {
+            clock,
+            overflow: Overflow::default(),
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Sets what happens to the events emitted past their rate, failing
+    /// their emit by default
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+}
+
+impl<E, C> RateLimit<E, C>
+where
+    E: Hash + Eq,
+    C: Clock,
+{
+    /// Allows `event` to be emitted up to `limit` times every `per`
+    pub fn limit(mut self, event: E, limit: usize, per: Duration) -> Self {
+        let bucket = Bucket::new(limit, per, self.clock.now());
+        self.buckets.insert(event, Mutex::new(bucket));
+        self
+    }
+}
+
+impl<E, C> EmitLimit<E> for RateLimit<E, C>
+where
+    E: Hash + Eq,
+    C: Clock,
+{
+    fn acquire(&self, event: &E) -> Result<Admission, Error> {
+        let Some(bucket) = self.buckets.get(event) else {
+            return Ok(Admission::Emit);
+        };
+
+        loop {
+            let mut bucket = bucket.lock().map_err(|_| Error::Poisoned)?;
+            let wait = match bucket.take(self.clock.now()) {
+                Ok(()) => return Ok(Admission::Emit),
+                Err(wait) => wait,
+            };
+
+            let rate_limited = Error::RateLimited {
+                limit: bucket.limit,
+                per: bucket.per,
+            };
+            match (self.overflow, wait) {
+                (Overflow::Drop, _) => return Ok(Admission::Drop),
+                (Overflow::Defer, Some(wait)) => {
+                    // Other emitters of the event can take the bucket while
+                    // this one waits, so it has to be checked again after
+                    drop(bucket);
+                    self.clock.sleep(wait);
+                }
+                (Overflow::Error, _) | (Overflow::Defer, None) => return Err(rate_limited),
+            }
+        }
+    }
+
+    fn release(&self, event: &E) {
+        if let Some(Ok(mut bucket)) = self.buckets.get(event).map(Mutex::lock) {
+            bucket.put_back();
+        }
+    }
+
+    fn reset(&self) {
+        let now = self.clock.now();
+        for mut bucket in self.buckets.values().filter_map(|b| b.lock().ok()) {
+            bucket.full_at = now;
+        }
+    }
+}
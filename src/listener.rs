@@ -0,0 +1,571 @@
+//! Bookkeeping shared by the listener registries of every bus flavor
+
+use std::{
+    any::Any,
+    cell::Cell,
+    collections::HashMap,
+    fmt,
+    hash::Hash,
+    marker::PhantomData,
+    mem::{self, Discriminant},
+    ops::{ControlFlow, Deref},
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::prelude::{BoxError, Error, SubscriptionId};
+
+/// What a listener is registered for
+#[derive(PartialEq, Eq, Hash)]
+pub(crate) enum Key<E> {
+    /// A specific event
+    Event(E),
+
+    /// Every instance of an enum variant, whatever data it carries
+    Variant(Discriminant<E>),
+
+    /// Every event emitted on the bus
+    Any,
+}
+
+/// The count of invocations left to a listener with a limit
+pub(crate) trait Counter {
+    fn new(n: usize) -> Self;
+
+    /// Takes one invocation, returning `false` if there is none left
+    fn claim(&self) -> bool;
+
+    fn left(&self) -> usize;
+}
+
+/// The count of a listener that is never shared between threads
+impl Counter for Cell<usize> {
+    fn new(n: usize) -> Self {
+        Cell::new(n)
+    }
+
+    fn claim(&self) -> bool {
+        match self.get().checked_sub(1) {
+            Some(n) => {
+                self.set(n);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn left(&self) -> usize {
+        self.get()
+    }
+}
+
+/// The count of a listener shared by all the snapshots holding a copy of it
+impl Counter for Arc<AtomicUsize> {
+    fn new(n: usize) -> Self {
+        Arc::new(AtomicUsize::new(n))
+    }
+
+    fn claim(&self) -> bool {
+        self.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok()
+    }
+
+    fn left(&self) -> usize {
+        self.load(Ordering::SeqCst)
+    }
+}
+
+/// A registered listener along with the id it was subscribed with
+#[derive(Clone)]
+pub(crate) struct Listener<H, C = Arc<AtomicUsize>> {
+    pub(crate) id: SubscriptionId,
+    pub(crate) handler: H,
+
+    /// Listeners with a higher priority run first
+    priority: i32,
+
+    /// Invocations left before the listener expires, if it has a limit
+    remaining: Option<C>,
+}
+
+impl<H, C: Counter> Listener<H, C> {
+    pub(crate) fn new(id: SubscriptionId, handler: H, priority: i32, limit: Option<usize>) -> Self {
+        Self {
+            id,
+            handler,
+            priority,
+            remaining: limit.map(C::new),
+        }
+    }
+
+    /// Claims one invocation of this listener, returning `false` if it
+    /// has already expired.
+    ///
+    /// The claim happens before the listener runs, so a listener that
+    /// re-emits the event it is handling won't be invoked past its limit.
+    pub(crate) fn claim(&self) -> bool {
+        self.remaining.as_ref().is_none_or(Counter::claim)
+    }
+
+    /// Whether this listener has used up all of its invocations
+    pub(crate) fn expired(&self) -> bool {
+        self.remaining.as_ref().is_some_and(|n| n.left() == 0)
+    }
+}
+
+impl<H, C> Listener<H, C> {
+    /// Inserts this listener in `listeners`, after every listener whose
+    /// priority is greater than or equal to its own, so that listeners
+    /// with the same priority run in registration order.
+    pub(crate) fn insert_into(self, listeners: &mut Vec<Self>) {
+        let index = listeners.partition_point(|l| l.priority >= self.priority);
+        listeners.insert(index, self);
+    }
+
+    /// Whether this listener is invoked before `other` when both receive
+    /// the same event
+    fn runs_before(&self, other: &Self) -> bool {
+        self.priority > other.priority || (self.priority == other.priority && self.id < other.id)
+    }
+}
+
+/// The failure of a listener whose result isn't of the type `emit_collect`
+/// is collecting
+#[derive(Debug)]
+pub(crate) struct TypeMismatch;
+
+impl fmt::Display for TypeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the listener doesn't return the type being collected")
+    }
+}
+
+impl std::error::Error for TypeMismatch {}
+
+/// A listener whose return values are gathered by `emit_collect`
+pub(crate) trait Returning<B, V> {
+    /// Invokes the listener, pushing its result to `results` if it is a
+    /// `Vec` of its return type and dropping it if there are none
+    ///
+    /// Returns false if the result was dropped because of a type mismatch.
+    fn call(&self, bus: &B, value: Option<&V>, results: Option<&mut dyn Any>) -> bool;
+}
+
+/// Adapts a closure returning `R` to `Returning`
+pub(crate) struct Returns<F, R>(pub(crate) F, pub(crate) PhantomData<fn() -> R>);
+
+impl<B, V, F, R> Returning<B, V> for Returns<F, R>
+where
+    F: Fn(&B, Option<&V>) -> R,
+    R: 'static,
+{
+    fn call(&self, bus: &B, value: Option<&V>, results: Option<&mut dyn Any>) -> bool {
+        let result = (self.0)(bus, value);
+        match results.map(|results| results.downcast_mut::<Vec<R>>()) {
+            Some(Some(results)) => results.push(result),
+            Some(None) => return false,
+            None => (),
+        }
+
+        true
+    }
+}
+
+/// The pointers a bus flavor keeps its handlers behind, implemented by the
+/// `BusRef` the handlers are invoked with
+pub(crate) trait Handlers<E, V>: Sized {
+    type Event: Deref<Target: Fn(&Self, Option<&V>)>;
+    type Any: Deref<Target: Fn(&Self, &E, Option<&V>)>;
+    type Mut: Deref<Target: Fn(&Self, &mut V)>;
+    type Fallible: Deref<Target: Fn(&Self, Option<&V>) -> Result<(), BoxError>>;
+    type Returning: Deref<Target: Returning<Self, V>>;
+    type Filter: Deref<Target: Fn(&E, Option<&V>) -> bool>;
+}
+
+/// The listeners a bus `B` can invoke
+pub(crate) enum Callback<E, V, B: Handlers<E, V>> {
+    Event(B::Event),
+    Any(B::Any),
+
+    /// A callback only invoked by `emit_mut`
+    Mut(B::Mut),
+
+    /// A callback whose failures are reported by `try_emit`
+    Fallible(B::Fallible),
+
+    /// A callback whose return value can be collected
+    Returning(B::Returning),
+
+    /// A callback only invoked for the emits accepted by a predicate
+    Filtered(B::Filter, Box<Callback<E, V, B>>),
+}
+
+impl<E, V, B: Handlers<E, V>> Callback<E, V, B> {
+    /// Invokes the callback, returning whether it ran rather than skipping
+    /// the emit, or its error if it is fallible and fails
+    pub(crate) fn call(&self, bus: &B, event: &E, value: Option<&V>) -> Result<bool, BoxError> {
+        match self {
+            Self::Event(handler) => handler(bus, value),
+            Self::Any(handler) => handler(bus, event, value),
+            Self::Mut(_) => return Ok(false),
+            Self::Fallible(handler) => handler(bus, value)?,
+            Self::Returning(handler) => {
+                handler.call(bus, value, None);
+            }
+            Self::Filtered(predicate, callback) => {
+                if !predicate(event, value) {
+                    return Ok(false);
+                }
+                return callback.call(bus, event, value);
+            }
+        }
+
+        Ok(true)
+    }
+
+    pub(crate) fn call_mut(&self, bus: &B, event: &E, value: &mut V) -> Result<bool, BoxError> {
+        match self {
+            Self::Mut(handler) => {
+                handler(bus, value);
+                Ok(true)
+            }
+            Self::Filtered(predicate, callback) if predicate(event, Some(value)) => {
+                callback.call_mut(bus, event, value)
+            }
+            Self::Filtered(..) => Ok(false),
+            _ => self.call(bus, event, Some(value)),
+        }
+    }
+
+    /// Invokes the callback like `call` does, pushing its result to
+    /// `results` if it has one
+    ///
+    /// Fails with a `TypeMismatch` if its result isn't of the type `results`
+    /// collects.
+    pub(crate) fn call_returning(
+        &self,
+        bus: &B,
+        event: &E,
+        value: Option<&V>,
+        results: &mut dyn Any,
+    ) -> Result<bool, BoxError> {
+        match self {
+            Self::Returning(handler) => {
+                if !handler.call(bus, value, Some(results)) {
+                    return Err(Box::new(TypeMismatch));
+                }
+                Ok(true)
+            }
+            Self::Filtered(predicate, callback) if predicate(event, value) => {
+                callback.call_returning(bus, event, value, results)
+            }
+            Self::Filtered(..) => Ok(false),
+            _ => self.call(bus, event, value),
+        }
+    }
+
+    pub(crate) fn filtered(self, predicate: B::Filter) -> Self {
+        Self::Filtered(predicate, Box::new(self))
+    }
+}
+
+/// Callbacks behind shared pointers are cloned along with the snapshots
+/// holding them
+impl<E, V, B> Clone for Callback<E, V, B>
+where
+    B: Handlers<E, V>,
+    B::Event: Clone,
+    B::Any: Clone,
+    B::Mut: Clone,
+    B::Fallible: Clone,
+    B::Returning: Clone,
+    B::Filter: Clone,
+{
+    fn clone(&self) -> Self {
+        match self {
+            Self::Event(handler) => Self::Event(handler.clone()),
+            Self::Any(handler) => Self::Any(handler.clone()),
+            Self::Mut(handler) => Self::Mut(handler.clone()),
+            Self::Fallible(handler) => Self::Fallible(handler.clone()),
+            Self::Returning(handler) => Self::Returning(handler.clone()),
+            Self::Filtered(predicate, callback) => {
+                Self::Filtered(predicate.clone(), callback.clone())
+            }
+        }
+    }
+}
+
+/// How the dispatch of an event went
+pub(crate) struct Dispatched {
+    /// Whether a listener stopped the propagation of the event
+    pub(crate) stopped: bool,
+
+    /// Whether any listener ran for the event, rather than skipping it
+    pub(crate) handled: bool,
+
+    /// Whether the limit policy of the bus discarded the event instead
+    pub(crate) dropped: bool,
+}
+
+impl Dispatched {
+    /// The dispatch of an event discarded by the limit policy of a bus
+    pub(crate) fn dropped() -> Self {
+        Self {
+            stopped: false,
+            handled: false,
+            dropped: true,
+        }
+    }
+
+    pub(crate) fn flow(&self) -> ControlFlow<()> {
+        if self.stopped {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+}
+
+/// Runs the listener `subscription` through `f`, turning a panic into an
+/// `Error::ListenerPanicked` instead of letting it unwind.
+pub(crate) fn isolate<T, F: FnOnce() -> T>(subscription: SubscriptionId, f: F) -> Result<T, Error> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            (*message).to_owned()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "Box<dyn Any>".to_owned()
+        };
+
+        Error::ListenerPanicked {
+            subscription,
+            message,
+        }
+    })
+}
+
+/// Iterates over several lists of listeners, each sorted by priority, in
+/// the order their listeners should be invoked.
+pub(crate) struct ByPriority<'a, H, C, const N: usize> {
+    lists: [&'a [Listener<H, C>]; N],
+}
+
+impl<'a, H, C, const N: usize> ByPriority<'a, H, C, N> {
+    pub(crate) fn new(lists: [&'a [Listener<H, C>]; N]) -> Self {
+        Self { lists }
+    }
+}
+
+impl<'a, H, C, const N: usize> Iterator for ByPriority<'a, H, C, N> {
+    type Item = &'a Listener<H, C>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut next: Option<usize> = None;
+        for (i, list) in self.lists.iter().enumerate() {
+            if let Some(head) = list.first() {
+                match next {
+                    Some(j) if !head.runs_before(&self.lists[j][0]) => (),
+                    _ => next = Some(i),
+                }
+            }
+        }
+
+        let list = &mut self.lists[next?];
+        let (head, rest) = list.split_first()?;
+        *list = rest;
+        Some(head)
+    }
+}
+
+/// A list of listeners sorted by priority, as kept by a `Registry`
+pub(crate) trait List: Default {
+    type Handler;
+    type Counter;
+
+    fn as_slice(&self) -> &[Listener<Self::Handler, Self::Counter>];
+}
+
+impl<H, C> List for Vec<Listener<H, C>> {
+    type Handler = H;
+    type Counter = C;
+
+    fn as_slice(&self) -> &[Listener<H, C>] {
+        self
+    }
+}
+
+/// Lists shared with in-flight emits, which are never mutated in place:
+/// changes are made to a copy that is then swapped in, leaving the emits
+/// with the snapshot they started from.
+impl<H> List for Arc<Vec<Listener<H>>> {
+    type Handler = H;
+    type Counter = Arc<AtomicUsize>;
+
+    fn as_slice(&self) -> &[Listener<H>] {
+        self
+    }
+}
+
+/// The listeners registered on a bus, by what they are registered for
+pub(crate) struct Registry<E, L> {
+    events: HashMap<E, L>,
+    variants: HashMap<Discriminant<E>, L>,
+    any: L,
+}
+
+impl<E, L: List> Registry<E, L> {
+    pub(crate) fn new() -> Self {
+        Self {
+            events: HashMap::new(),
+            variants: HashMap::new(),
+            any: L::default(),
+        }
+    }
+
+    /// The listeners registered for `event` specifically
+    pub(crate) fn event(&self, event: &E) -> Option<&L>
+    where
+        E: Hash + Eq,
+    {
+        self.events.get(event)
+    }
+
+    /// The listeners registered for the variant of `event`
+    pub(crate) fn variant(&self, event: &E) -> Option<&L> {
+        // Spares emits the discriminant and lookup when no one listens to
+        // variants, which is the common case
+        if self.variants.is_empty() {
+            return None;
+        }
+        self.variants.get(&mem::discriminant(event))
+    }
+
+    /// The listeners registered for every event
+    pub(crate) fn any(&self) -> &L {
+        &self.any
+    }
+
+    /// The listeners registered for `key`
+    pub(crate) fn list(&self, key: &Key<E>) -> Option<&L>
+    where
+        E: Hash + Eq,
+    {
+        match key {
+            Key::Event(event) => self.events.get(event),
+            Key::Variant(variant) => self.variants.get(variant),
+            Key::Any => Some(&self.any),
+        }
+    }
+
+    /// Drops every listener
+    pub(crate) fn clear(&mut self) {
+        self.events.clear();
+        self.variants.clear();
+        self.any = L::default();
+    }
+
+    /// Whether a listener matching `predicate` is registered
+    pub(crate) fn contains<P>(&self, predicate: P) -> bool
+    where
+        P: Fn(&Listener<L::Handler, L::Counter>) -> bool,
+    {
+        self.events
+            .values()
+            .chain(self.variants.values())
+            .chain([&self.any])
+            .flat_map(|list| list.as_slice())
+            .any(predicate)
+    }
+}
+
+impl<E, H, C> Registry<E, Vec<Listener<H, C>>> {
+    pub(crate) fn insert(&mut self, key: Key<E>, listener: Listener<H, C>)
+    where
+        E: Hash + Eq,
+    {
+        let list = match key {
+            Key::Event(event) => self.events.entry(event).or_default(),
+            Key::Variant(variant) => self.variants.entry(variant).or_default(),
+            Key::Any => &mut self.any,
+        };
+        listener.insert_into(list);
+    }
+
+    /// Removes the listeners matching `predicate`, returning whether there
+    /// was any
+    pub(crate) fn remove_where<P>(&mut self, predicate: P) -> bool
+    where
+        P: Fn(&Listener<H, C>) -> bool,
+    {
+        let mut removed = false;
+        let mut remove_from = |list: &mut Vec<Listener<H, C>>| {
+            let len = list.len();
+            list.retain(|l| !predicate(l));
+            removed |= list.len() != len;
+        };
+
+        self.events.retain(|_, list| {
+            remove_from(list);
+            !list.is_empty()
+        });
+        self.variants.retain(|_, list| {
+            remove_from(list);
+            !list.is_empty()
+        });
+        remove_from(&mut self.any);
+
+        removed
+    }
+}
+
+/// Registries of snapshots are updated in two steps: the new snapshots are
+/// built from the current ones without holding the lock of the registry,
+/// then swapped in while holding it only briefly.
+impl<E, H> Registry<E, Arc<Vec<Listener<H>>>> {
+    /// Swaps in `list` as the snapshot of `key`
+    pub(crate) fn set(&mut self, key: Key<E>, list: Arc<Vec<Listener<H>>>)
+    where
+        E: Hash + Eq,
+    {
+        match key {
+            Key::Event(event) => self.events.insert(event, list),
+            Key::Variant(variant) => self.variants.insert(variant, list),
+            Key::Any => Some(mem::replace(&mut self.any, list)),
+        };
+    }
+
+    /// The snapshots holding a listener that matches `predicate`
+    pub(crate) fn matching<P>(&self, predicate: P) -> Vec<Arc<Vec<Listener<H>>>>
+    where
+        P: Fn(&Listener<H>) -> bool,
+    {
+        self.events
+            .values()
+            .chain(self.variants.values())
+            .chain([&self.any])
+            .filter(|list| list.iter().any(&predicate))
+            .cloned()
+            .collect()
+    }
+
+    /// Swaps in `new` wherever `old` is the current snapshot, dropping the
+    /// snapshots that are left empty
+    pub(crate) fn replace(&mut self, old: &Arc<Vec<Listener<H>>>, new: Arc<Vec<Listener<H>>>) {
+        let keep = |list: &mut Arc<Vec<Listener<H>>>| {
+            if Arc::ptr_eq(list, old) {
+                *list = Arc::clone(&new);
+            }
+            !list.is_empty()
+        };
+
+        self.events.retain(|_, list| keep(list));
+        self.variants.retain(|_, list| keep(list));
+        if Arc::ptr_eq(&self.any, old) {
+            self.any = Arc::clone(&new);
+        }
+    }
+}
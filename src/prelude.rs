@@ -1,23 +1,263 @@
 use std::{
     cell::{Cell, RefCell},
     collections::HashMap,
+    fmt,
     hash::Hash,
+    marker::PhantomData,
+    mem,
+    ops::ControlFlow,
+    rc::Rc,
+    time::Duration,
 };
 
-#[derive(Debug, PartialEq)]
+pub use crate::limit::EmitLimit;
+use crate::limit::{Admission, Total, Unlimited};
+use crate::listener::{
+    self, ByPriority, Dispatched, Handlers, Key, Listener, Registry, Returning, Returns,
+    TypeMismatch,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum Error {
-    /// Usually fired then a lock or a borrow cannot be obtained for a bus
-    BusLock,
+    /// Fired when an unsync bus can't be borrowed because an operation
+    /// already in progress on it holds it, as opposed to a lock failure
+    Reentrant,
+
+    /// Fired when a lock of a sync bus has been poisoned by a thread that
+    /// panicked while holding it
+    Poisoned,
 
     /// Fired when a bus has reached its event count limit (if it has one)
-    Disconnected,
+    Disconnected { limit: usize, count: usize },
+
+    /// Fired when emitting an event that has reached its own emit limit,
+    /// while other events can still be emitted
+    EventDisconnected { limit: usize, count: usize },
+
+    /// Fired when a bus has emitted as many events as its limit allows
+    /// within a span of time, until some of them fall out of it
+    Throttled { limit: usize, window: Duration },
+
+    /// Fired when emitting an event that has exceeded its rate limit
+    RateLimited { limit: usize, per: Duration },
+
+    /// Fired when a custom limit policy refuses to emit an event, for the
+    /// given reason
+    Rejected { reason: String },
+
+    /// Fired in strict mode when adding a listener would bring an event
+    /// over its max number of listeners
+    TooManyListeners { limit: usize, count: usize },
+
+    /// Fired when using a bus that has been closed
+    Closed,
+
+    /// Fired when adding a listener limited to zero invocations
+    InvalidLimit,
+
+    /// Fired when a topic or a topic pattern is malformed
+    InvalidTopic,
+
+    /// Reported to the error hook of a bus when one of its fallible
+    /// listeners fails outside of a `try_emit`
+    ListenerFailed {
+        subscription: SubscriptionId,
+        message: String,
+    },
+
+    /// Reported to the error hook of a bus isolating panics when one of
+    /// its listeners panics
+    ListenerPanicked {
+        subscription: SubscriptionId,
+        message: String,
+    },
+
+    /// Fired by `emit_collect` when one of the listeners it invoked returns
+    /// another type than the one being collected
+    ReturnTypeMismatch { subscription: SubscriptionId },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Reentrant => write!(f, "the bus is already being modified"),
+            Self::Poisoned => write!(f, "the bus lock has been poisoned"),
+            Self::Disconnected { limit, count } => write!(
+                f,
+                "the bus has reached its limit of {} emits ({} emitted)",
+                limit, count
+            ),
+            Self::EventDisconnected { limit, count } => write!(
+                f,
+                "the event has reached its limit of {} emits ({} emitted)",
+                limit, count
+            ),
+            Self::Throttled { limit, window } => write!(
+                f,
+                "the bus has reached its limit of {} emits every {:?}",
+                limit, window
+            ),
+            Self::RateLimited { limit, per } => write!(
+                f,
+                "the event has exceeded its rate of {} emits every {:?}",
+                limit, per
+            ),
+            Self::Rejected { reason } => write!(f, "the emit was rejected: {}", reason),
+            Self::TooManyListeners { limit, count } => write!(
+                f,
+                "the event would have {} listeners, over its limit of {}",
+                count, limit
+            ),
+            Self::Closed => write!(f, "the bus has been closed"),
+            Self::InvalidLimit => write!(f, "a listener can't be limited to zero invocations"),
+            Self::InvalidTopic => write!(f, "malformed topic or topic pattern"),
+            Self::ListenerFailed {
+                subscription,
+                message,
+            } => write!(f, "listener {} failed: {}", subscription, message),
+            Self::ListenerPanicked {
+                subscription,
+                message,
+            } => write!(f, "listener {} panicked: {}", subscription, message),
+            Self::ReturnTypeMismatch { subscription } => write!(
+                f,
+                "listener {} doesn't return the type being collected",
+                subscription
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The error a fallible listener can fail with
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A failure of a listener added with `try_on`
+#[derive(Debug)]
+pub struct ListenerFailure {
+    /// The listener that failed
+    pub subscription: SubscriptionId,
+    pub error: BoxError,
+}
+
+impl fmt::Display for ListenerFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "listener {} failed: {}", self.subscription, self.error)
+    }
+}
+
+impl std::error::Error for ListenerFailure {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+/// The ways a `try_emit` can fail
+#[derive(Debug)]
+pub enum EmitError<E> {
+    /// The event could not be emitted at all
+    Bus(Error),
+
+    /// Some of the listeners failed, in the order they were invoked
+    Listeners {
+        event: E,
+        failures: Vec<ListenerFailure>,
+    },
+}
+
+impl<E> From<Error> for EmitError<E> {
+    fn from(error: Error) -> Self {
+        Self::Bus(error)
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for EmitError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bus(error) => error.fmt(f),
+            Self::Listeners { event, failures } => write!(
+                f,
+                "{} listener(s) failed handling {:?}",
+                failures.len(),
+                event
+            ),
+        }
+    }
+}
+
+impl<E: fmt::Debug> std::error::Error for EmitError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Bus(error) => Some(error),
+            Self::Listeners { failures, .. } => failures
+                .first()
+                .map(|failure| failure as &(dyn std::error::Error + 'static)),
+        }
+    }
+}
+
+/// Identifies a listener registered on a bus, as returned by `on`.
+///
+/// Pass it to `off` to remove the listener. Ids are only meaningful for the
+/// bus that issued them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SubscriptionId(usize);
+
+impl SubscriptionId {
+    pub(crate) fn new(id: usize) -> Self {
+        Self(id)
+    }
+}
+
+impl fmt::Display for SubscriptionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
 }
 
 pub trait EventEmitter<E, V> {
-    /// Adds a listener `f` for and `event`
-    fn on<F>(&self, event: E, f: F) -> Result<(), Error>
+    /// The bus reference handed to listeners when they are invoked
+    type Ref;
+
+    /// The boxed form in which listeners are stored by the bus
+    type Handler: ?Sized;
+
+    /// Adds a listener `f` for and `event`, returning the id of
+    /// the subscription
+    fn on<F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self::Ref, Option<&V>) + IntoHandler<Self::Handler>;
+
+    /// Adds a listener `f` for `event` with the given `priority`.
+    ///
+    /// Listeners with a higher priority are invoked before those with a
+    /// lower one, regardless of the order they were registered in. Listeners
+    /// added with `on` have a priority of `0`; listeners sharing the same
+    /// priority run in registration order.
+    fn on_with_priority<F>(&self, event: E, priority: i32, f: F) -> Result<SubscriptionId, Error>
     where
-        F: Fn(&BusRef<E, V>, Option<&V>) + 'static;
+        F: Fn(&Self::Ref, Option<&V>) + IntoHandler<Self::Handler>;
+
+    /// Adds a listener `f` for `event` that is removed after it has
+    /// fired `n` times, failing with `Error::InvalidLimit` if `n` is zero.
+    fn on_n<F>(&self, event: E, n: usize, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self::Ref, Option<&V>) + IntoHandler<Self::Handler>;
+
+    /// Adds a listener `f` for `event` that is removed after it has fired once
+    fn once<F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self::Ref, Option<&V>) + IntoHandler<Self::Handler>,
+    {
+        self.on_n(event, 1, f)
+    }
+
+    /// Removes the listener identified by `subscription`.
+    ///
+    /// Returns `false` if no such listener was registered on this bus.
+    fn off(&self, subscription: SubscriptionId) -> Result<bool, Error>;
 
     /// Emits an `event` with a `value` associated to it,
     /// firing all listeners connected to it via `on`.
@@ -31,70 +271,887 @@ pub trait EventEmitter<E, V> {
     }
 }
 
-/// Inner implementation of a bus structure
+/// Conversion of a listener closure into the boxed handler `H` a bus stores.
+///
+/// Each bus flavor places its own requirements on listeners (e.g. `sync` buses
+/// require them to be `Send + Sync`), this trait is what enforces them.
+pub trait IntoHandler<H: ?Sized> {
+    fn into_handler(self) -> Box<H>;
+}
+
+/// An enum of events whose variants carry their own payload.
+///
+/// Buses of such events are keyed by `Kind`, a field-less enum with the
+/// same variants, and the whole event is passed along as the emitted value.
+/// Rather than implementing it by hand, derive it with `#[derive(Event)]`
+/// (requires the `derive` feature), which also generates a `Variant` for
+/// each variant of the enum.
+pub trait Event {
+    /// The field-less counterpart of this enum, used as the bus key
+    type Kind: Hash + Eq;
+
+    fn kind(&self) -> Self::Kind;
+}
+
+/// A single variant of an `Event` enum, along with the payload it carries.
+///
+/// Used to register listeners for that variant receiving the payload itself
+/// through `on_event`.
+pub trait Variant<E: Event> {
+    /// The data carried by the variant, `()` if it carries none
+    type Payload;
+
+    /// The kind of the events of this variant
+    fn kind() -> E::Kind;
+
+    /// The payload of `event`, if it is of this variant
+    fn payload(event: &E) -> Option<&Self::Payload>;
+}
+
+#[cfg(feature = "derive")]
+pub use tram_derive::Event;
+
+/// A listener as stored by an unsync `BusRef`
+pub type Handler<E, V> = dyn Fn(&BusRef<E, V>, Option<&V>);
+
+impl<E, V, F> IntoHandler<Handler<E, V>> for F
+where
+    F: Fn(&BusRef<E, V>, Option<&V>) + 'static,
+{
+    fn into_handler(self) -> Box<Handler<E, V>> {
+        Box::new(self)
+    }
+}
+
+/// A listener receiving every event emitted on an unsync `BusRef`
+type AnyHandler<E, V> = dyn Fn(&BusRef<E, V>, &E, Option<&V>);
+
+/// A listener allowed to modify the value of an `emit_mut`
+type MutHandler<E, V> = dyn Fn(&BusRef<E, V>, &mut V);
+
+/// A listener that can fail
+type FallibleHandler<E, V> = dyn Fn(&BusRef<E, V>, Option<&V>) -> Result<(), BoxError>;
+
+/// A hook receiving the errors that can't be returned to an emitter
+type ErrorHook = dyn Fn(&Error);
+
+/// A hook of `BusRef::on_close`
+type CloseHook<E, V> = dyn Fn(&BusRef<E, V>);
+
+/// The hook of `BusRef::on_max_listeners`
+type MaxListenersHook<E, V> = dyn Fn(&BusRef<E, V>, &E, usize);
+
+/// A predicate deciding whether a listener should receive an emit
+type Filter<E, V> = dyn Fn(&E, Option<&V>) -> bool;
+
+/// The listeners an unsync `BusRef` can invoke
+type Callback<E, V> = listener::Callback<E, V, BusRef<E, V>>;
+
+impl<E, V> Handlers<E, V> for BusRef<E, V> {
+    type Event = Box<Handler<E, V>>;
+    type Any = Box<AnyHandler<E, V>>;
+    type Mut = Box<MutHandler<E, V>>;
+    type Fallible = Box<FallibleHandler<E, V>>;
+    type Returning = Box<dyn Returning<Self, V>>;
+    type Filter = Box<Filter<E, V>>;
+}
+
+type Listeners<E, V> = Registry<E, Vec<Entry<E, V>>>;
+
+/// A listener as registered on an unsync `BusRef`
+type Entry<E, V> = Listener<Callback<E, V>, Cell<usize>>;
+
+/// A change to the listeners of a bus requested while it was dispatching
+enum Mutation<E, V> {
+    Add(Key<E>, Entry<E, V>),
+    Remove(SubscriptionId),
+}
+
+/// Inner implementation of a bus structure.
+///
+/// The set of listeners is fixed for the duration of an emit: listeners
+/// added or removed while an event is being dispatched (e.g. from within
+/// another listener) are queued and applied once the outermost emit
+/// completes. This means that new listeners won't receive the event being
+/// dispatched, nor any event re-emitted while dispatching it, and removed
+/// listeners will still receive them.
 pub struct BusRef<E, V> {
-    marker: std::marker::PhantomData<E>,
-    listeners: RefCell<HashMap<E, Vec<Box<dyn Fn(&Self, Option<&V>)>>>>,
+    listeners: RefCell<Listeners<E, V>>,
+    pending: RefCell<Vec<Mutation<E, V>>>,
+    dispatch_depth: Cell<usize>,
+    propagation_stopped: Cell<bool>,
+    stop_on_error: Cell<bool>,
+    catch_panics: Cell<bool>,
+    error_hook: RefCell<Option<Rc<ErrorHook>>>,
+    close_hooks: RefCell<Vec<Box<CloseHook<E, V>>>>,
+    closed: Cell<bool>,
+    max_listeners: RefCell<HashMap<E, usize>>,
+    strict_max_listeners: Cell<bool>,
+    max_listeners_hook: RefCell<Option<Rc<MaxListenersHook<E, V>>>>,
+    dead_letter: RefCell<Option<Rc<AnyHandler<E, V>>>>,
+    unhandled_count: Cell<usize>,
+    next_id: Cell<usize>,
+    has_expired: Cell<bool>,
     emit_count: Cell<usize>,
-    emit_limit: usize,
+    emit_limit: Box<dyn EmitLimit<E>>,
+}
+
+/// Marks an emit in progress on a bus, ending it when dropped even if one
+/// of the listeners panics.
+struct Dispatch<'a, E, V> {
+    bus: &'a BusRef<E, V>,
+
+    /// Whether the propagation of the emit this one interrupted was stopped
+    outer_stopped: bool,
+}
+
+impl<E, V> Dispatch<'_, E, V> {
+    /// Whether a listener stopped the propagation of the emit
+    fn stopped(&self) -> bool {
+        self.bus.propagation_stopped.get()
+    }
+}
+
+impl<E, V> Drop for Dispatch<'_, E, V> {
+    fn drop(&mut self) {
+        let depth = &self.bus.dispatch_depth;
+        depth.set(depth.get() - 1);
+        self.bus.propagation_stopped.set(self.outer_stopped);
+    }
 }
 
 impl<E, V> BusRef<E, V> {
     pub(crate) fn unbound() -> Self {
-        Self {
-            marker: std::marker::PhantomData,
-            listeners: RefCell::new(HashMap::new()),
-            emit_count: Cell::new(0),
-            emit_limit: 0,
-        }
+        Self::with_limit(Box::new(Unlimited))
     }
 
     pub(crate) fn bound(max_emit_count: usize) -> Self {
+        Self::with_limit(Box::new(Total::new(max_emit_count)))
+    }
+
+    pub(crate) fn with_limit(emit_limit: Box<dyn EmitLimit<E>>) -> Self {
         Self {
-            marker: std::marker::PhantomData,
-            listeners: RefCell::new(HashMap::new()),
+            listeners: RefCell::new(Registry::new()),
+            pending: RefCell::new(Vec::new()),
+            dispatch_depth: Cell::new(0),
+            propagation_stopped: Cell::new(false),
+            stop_on_error: Cell::new(false),
+            catch_panics: Cell::new(false),
+            error_hook: RefCell::new(None),
+            dead_letter: RefCell::new(None),
+            close_hooks: RefCell::new(Vec::new()),
+            closed: Cell::new(false),
+            max_listeners: RefCell::new(HashMap::new()),
+            strict_max_listeners: Cell::new(false),
+            max_listeners_hook: RefCell::new(None),
+            unhandled_count: Cell::new(0),
+            next_id: Cell::new(0),
+            has_expired: Cell::new(false),
             emit_count: Cell::new(0),
-            emit_limit: max_emit_count,
+            emit_limit,
         }
     }
 
     pub fn disconnected(&self) -> bool {
-        let event_count = self.event_count();
-        event_count != 0 && event_count == self.emit_limit
+        self.emit_limit.disconnected()
     }
 
     pub fn event_count(&self) -> usize {
         self.emit_count.get()
     }
+
+    fn next_subscription(&self) -> SubscriptionId {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        SubscriptionId::new(id)
+    }
+
+    fn begin_dispatch(&self) -> Dispatch<'_, E, V> {
+        self.dispatch_depth.set(self.dispatch_depth.get() + 1);
+        Dispatch {
+            bus: self,
+            outer_stopped: self.propagation_stopped.replace(false),
+        }
+    }
+
+    /// Whether an event is being dispatched on this bus
+    fn dispatching(&self) -> bool {
+        self.dispatch_depth.get() != 0
+    }
+
+    /// Returns `true` if this bus has been closed with `close`
+    pub fn is_closed(&self) -> bool {
+        self.closed.get()
+    }
+
+    /// Closes this bus, after which adding listeners or emitting events on
+    /// it fails with `Error::Closed`, then runs the hooks set with
+    /// `on_close`, in the order they were added.
+    ///
+    /// The listeners and hooks of the bus are dropped along the way, the
+    /// listeners of an event being dispatched once its emit completes.
+    /// Closing a bus that was already closed does nothing.
+    pub fn close(&self) {
+        if self.closed.replace(true) {
+            return;
+        }
+
+        if let Ok(mut listeners) = self.listeners.try_borrow_mut() {
+            listeners.clear();
+        }
+        self.pending.take();
+        self.error_hook.take();
+        self.dead_letter.take();
+        self.max_listeners_hook.take();
+
+        let hooks = self.close_hooks.take();
+        for hook in hooks {
+            hook(self);
+        }
+    }
+
+    /// Adds a hook that will call `f` once this bus gets closed.
+    pub fn on_close<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: Fn(&Self) + 'static,
+    {
+        if self.is_closed() {
+            return Err(Error::Closed);
+        }
+
+        self.close_hooks
+            .try_borrow_mut()
+            .map_err(|_| Error::Reentrant)?
+            .push(Box::new(f));
+        Ok(())
+    }
+
+    /// Resets the count of events emitted on this bus along with its limit
+    /// policy, so that a bus that reached its bounds can emit again.
+    pub fn reset(&self) -> Result<(), Error> {
+        if self.is_closed() {
+            return Err(Error::Closed);
+        }
+
+        self.emit_limit.reset();
+        self.emit_count.set(0);
+        Ok(())
+    }
+
+    /// Sets whether `try_emit` stops at the first listener that fails,
+    /// rather than running every listener and reporting all failures.
+    pub fn set_stop_on_error(&self, stop: bool) {
+        self.stop_on_error.set(stop);
+    }
+
+    fn stops_on_error(&self) -> bool {
+        self.stop_on_error.get()
+    }
+
+    /// Sets whether panics of the listeners of this bus are caught, so that
+    /// the listeners after them still run and the emit completes normally.
+    ///
+    /// Caught panics are reported to the hook set with `on_error` as an
+    /// `Error::ListenerPanicked`. Note that the panic hook of the process
+    /// still runs when a listener panics.
+    pub fn set_catch_panics(&self, catch: bool) {
+        self.catch_panics.set(catch);
+    }
+
+    /// Sets the hook receiving the errors this bus can't return to an
+    /// emitter, replacing the previous one.
+    pub fn on_error<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: Fn(&Error) + 'static,
+    {
+        let mut hook = self
+            .error_hook
+            .try_borrow_mut()
+            .map_err(|_| Error::Reentrant)?;
+        *hook = Some(Rc::new(f));
+        Ok(())
+    }
+
+    /// Sets the handler receiving the events emitted while no listener
+    /// was there to receive them, along with their value, replacing the
+    /// previous one.
+    ///
+    /// Listeners skipping an emit, like a filtered one whose predicate
+    /// rejects it or one added with `on_mut` on any other emit than
+    /// `emit_mut`, don't count as receiving it.
+    pub fn on_unhandled<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: Fn(&Self, &E, Option<&V>) + 'static,
+    {
+        let mut handler = self
+            .dead_letter
+            .try_borrow_mut()
+            .map_err(|_| Error::Reentrant)?;
+        *handler = Some(Rc::new(f));
+        Ok(())
+    }
+
+    /// Sets whether adding a listener that brings an event over its max
+    /// number of listeners fails with `Error::TooManyListeners`, rather
+    /// than warning the hook set with `on_max_listeners`.
+    pub fn set_strict_max_listeners(&self, strict: bool) {
+        self.strict_max_listeners.set(strict);
+    }
+
+    /// Sets the hook warned when an event gets more listeners than its max
+    /// number of listeners, along with their count, replacing the previous
+    /// one.
+    ///
+    /// The hook is warned when the count goes over the max, not for every
+    /// listener added past it.
+    pub fn on_max_listeners<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: Fn(&Self, &E, usize) + 'static,
+    {
+        let mut hook = self
+            .max_listeners_hook
+            .try_borrow_mut()
+            .map_err(|_| Error::Reentrant)?;
+        *hook = Some(Rc::new(f));
+        Ok(())
+    }
+
+    /// The number of events emitted on this bus that no listener received
+    pub fn unhandled_count(&self) -> usize {
+        self.unhandled_count.get()
+    }
+
+    /// Counts `event` as unhandled and hands it to the dead letter handler
+    fn dead_letter(&self, event: &E, value: Option<&V>) {
+        self.unhandled_count.set(self.unhandled_count.get() + 1);
+        let handler = self.dead_letter.borrow().clone();
+        if let Some(handler) = handler {
+            handler(self, event, value);
+        }
+    }
+
+    /// Reports the failure of the listener `subscription`, if it failed,
+    /// returning whether it ran
+    fn report_failure(&self, subscription: SubscriptionId, result: Result<bool, BoxError>) -> bool {
+        match result {
+            Ok(ran) => ran,
+            Err(error) => {
+                self.report(Error::ListenerFailed {
+                    subscription,
+                    message: error.to_string(),
+                });
+                true
+            }
+        }
+    }
+
+    /// Hands `error` to the error hook, if there is one
+    fn report(&self, error: Error) {
+        let hook = self.error_hook.borrow().clone();
+        if let Some(hook) = hook {
+            hook(&error);
+        }
+    }
+
+    /// Stops the event being dispatched from reaching the listeners that
+    /// would run after the current one.
+    ///
+    /// Meant to be called by listeners on the bus they receive: it only
+    /// affects the innermost emit in progress on this bus and has no effect
+    /// outside of an emit.
+    pub fn stop_propagation(&self) {
+        if self.dispatching() {
+            self.propagation_stopped.set(true);
+        }
+    }
+
+    /// Drops listeners that have used up their invocations. This is a no-op
+    /// while an event is being dispatched, in which case the outermost emit
+    /// takes care of it.
+    fn purge_expired(&self) {
+        if !self.has_expired.get() {
+            return;
+        }
+
+        if let Ok(mut listeners) = self.listeners.try_borrow_mut() {
+            listeners.remove_where(|l| l.expired());
+            self.has_expired.set(false);
+        }
+    }
+
+    /// Removes the listener identified by `subscription`, if it is
+    /// still registered on this bus.
+    pub(crate) fn remove_listener(&self, subscription: SubscriptionId) -> Result<bool, Error> {
+        if self.dispatching() {
+            let registered = self.is_registered(subscription);
+            self.pending
+                .borrow_mut()
+                .push(Mutation::Remove(subscription));
+            return Ok(registered);
+        }
+
+        match self.listeners.try_borrow_mut() {
+            Ok(mut listeners) => Ok(listeners.remove_where(|l| l.id == subscription)),
+            Err(_) => Err(Error::Reentrant),
+        }
+    }
+
+    /// Whether `subscription` is live, taking into account the mutations
+    /// queued while dispatching
+    fn is_registered(&self, subscription: SubscriptionId) -> bool {
+        let mut registered = self
+            .listeners
+            .borrow()
+            .contains(|l| l.id == subscription && !l.expired());
+
+        for mutation in self.pending.borrow().iter() {
+            match mutation {
+                Mutation::Add(_, l) if l.id == subscription => registered = true,
+                Mutation::Remove(id) if *id == subscription => registered = false,
+                _ => (),
+            }
+        }
+
+        registered
+    }
+}
+
+impl<E, V> BusRef<E, V>
+where
+    E: Hash + Eq,
+{
+    /// Applies the mutations queued while dispatching
+    fn apply_pending(&self) -> Result<(), Error> {
+        let pending = self.pending.take();
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut listeners = self
+            .listeners
+            .try_borrow_mut()
+            .map_err(|_| Error::Reentrant)?;
+        for mutation in pending {
+            match mutation {
+                Mutation::Add(key, listener) => listeners.insert(key, listener),
+                Mutation::Remove(subscription) => {
+                    listeners.remove_where(|l| l.id == subscription);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_listener(
+        &self,
+        key: Key<E>,
+        callback: Callback<E, V>,
+        priority: i32,
+        limit: Option<usize>,
+    ) -> Result<SubscriptionId, Error> {
+        if self.is_closed() {
+            return Err(Error::Closed);
+        }
+
+        if limit == Some(0) {
+            return Err(Error::InvalidLimit);
+        }
+
+        if let Key::Event(event) = &key {
+            self.watch_listeners(event)?;
+        }
+
+        let id = self.next_subscription();
+        let listener = Listener::new(id, callback, priority, limit);
+
+        if self.dispatching() {
+            self.pending.borrow_mut().push(Mutation::Add(key, listener));
+            return Ok(id);
+        }
+
+        match self.listeners.try_borrow_mut() {
+            Ok(mut listeners) => {
+                listeners.insert(key, listener);
+                Ok(id)
+            }
+            Err(_) => Err(Error::Reentrant),
+        }
+    }
+
+    /// Sets the max number of listeners of `event`, past which adding
+    /// another one is reported as a likely leak of subscriptions.
+    pub fn set_max_listeners(&self, event: E, max: usize) -> Result<(), Error> {
+        self.max_listeners
+            .try_borrow_mut()
+            .map_err(|_| Error::Reentrant)?
+            .insert(event, max);
+        Ok(())
+    }
+
+    /// The number of listeners of `event`, including the ones added while
+    /// dispatching that are yet to be applied, but not the listeners of
+    /// its variant nor of any event.
+    pub fn listener_count(&self, event: &E) -> usize {
+        let registered = self.listeners.borrow().event(event).map_or(0, Vec::len);
+        let pending = self
+            .pending
+            .borrow()
+            .iter()
+            .filter(|m| matches!(m, Mutation::Add(Key::Event(e), _) if e == event))
+            .count();
+        registered + pending
+    }
+
+    /// Checks that `event` can get another listener, warning the hook set
+    /// with `on_max_listeners` or failing in strict mode when it can't.
+    fn watch_listeners(&self, event: &E) -> Result<(), Error> {
+        let limit = match self.max_listeners.borrow().get(event) {
+            Some(limit) => *limit,
+            None => return Ok(()),
+        };
+
+        let count = self.listener_count(event) + 1;
+        if count <= limit {
+            return Ok(());
+        }
+
+        if self.strict_max_listeners.get() {
+            return Err(Error::TooManyListeners { limit, count });
+        }
+
+        let hook = self.max_listeners_hook.borrow().clone();
+        if let (Some(hook), true) = (hook, count == limit + 1) {
+            hook(self, event, count);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the limit policy of this bus doesn't let it emit
+    /// `event` anymore, while other events may still be emitted.
+    pub fn event_disconnected(&self, event: &E) -> bool {
+        self.emit_limit.event_disconnected(event)
+    }
+
+    /// Invokes the listeners of `event` in order through `invoke`, then
+    /// applies the changes to the listeners requested meanwhile.
+    fn dispatch<F>(&self, event: &E, mut invoke: F) -> Result<Dispatched, Error>
+    where
+        F: FnMut(&Entry<E, V>) -> bool,
+    {
+        if self.is_closed() {
+            return Err(Error::Closed);
+        }
+
+        if self.emit_limit.acquire(event)? == Admission::Drop {
+            return Ok(Dispatched::dropped());
+        }
+
+        self.emit_count.set(self.emit_count.get() + 1);
+
+        let mut handled = false;
+        let stopped = {
+            let dispatch = self.begin_dispatch();
+            let listeners = self.listeners.borrow();
+            let for_event = listeners.event(event).map(Vec::as_slice);
+            let for_variant = listeners.variant(event).map(Vec::as_slice);
+            let lists = [
+                for_event.unwrap_or_default(),
+                for_variant.unwrap_or_default(),
+                listeners.any(),
+            ];
+
+            for l in ByPriority::new(lists).filter(|l| l.claim()) {
+                handled |= if self.catch_panics.get() {
+                    listener::isolate(l.id, || invoke(l)).unwrap_or_else(|error| {
+                        self.report(error);
+                        true
+                    })
+                } else {
+                    invoke(l)
+                };
+                if l.expired() {
+                    self.has_expired.set(true);
+                }
+                if dispatch.stopped() {
+                    break;
+                }
+            }
+
+            dispatch.stopped()
+        };
+
+        if !self.dispatching() {
+            self.apply_pending()?;
+            self.purge_expired();
+            if self.is_closed() {
+                self.listeners
+                    .try_borrow_mut()
+                    .map_err(|_| Error::Reentrant)?
+                    .clear();
+            }
+        }
+
+        Ok(Dispatched {
+            stopped,
+            handled,
+            dropped: false,
+        })
+    }
+
+    /// Emits an `event` with a `value` associated to it like
+    /// `emit_with_value` does, reporting whether a listener halted it with
+    /// `stop_propagation` before it reached every listener.
+    pub fn emit_controlled(&self, event: E, value: Option<&V>) -> Result<ControlFlow<()>, Error> {
+        self.dispatch(&event, |l| {
+            self.report_failure(l.id, l.handler.call(self, &event, value))
+        })
+        .map(|dispatched| {
+            self.settle(&dispatched, &event, value);
+            dispatched.flow()
+        })
+    }
+
+    /// Hands `event` over to the dead letter handler if no listener
+    /// received it
+    fn settle(&self, dispatched: &Dispatched, event: &E, value: Option<&V>) {
+        if !dispatched.handled && !dispatched.dropped {
+            self.dead_letter(event, value);
+        }
+    }
+
+    /// Adds a listener for `event` that will call `f` with a mutable
+    /// reference to the value of every `emit_mut` of said event.
+    ///
+    /// Such listeners are not invoked by `emit` and `emit_with_value`.
+    pub fn on_mut<F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self, &mut V) + 'static,
+    {
+        self.add_listener(Key::Event(event), Callback::Mut(Box::new(f)), 0, None)
+    }
+
+    /// Emits an `event` with a `value` that listeners added with `on_mut`
+    /// can modify, firing all listeners connected to it.
+    ///
+    /// Listeners run in the usual order, each receiving the value as left
+    /// by the ones before it, so once this returns `value` holds the
+    /// outcome of the whole pipeline.
+    pub fn emit_mut(&self, event: E, value: &mut V) -> Result<(), Error> {
+        self.dispatch(&event, |l| {
+            self.report_failure(l.id, l.handler.call_mut(self, &event, value))
+        })
+        .map(|dispatched| {
+            self.settle(&dispatched, &event, Some(value));
+        })
+    }
+
+    /// Adds a listener for `event` that can fail, whose errors are
+    /// reported by `try_emit`.
+    ///
+    /// On any other emit its errors are reported to the hook set with
+    /// `on_error` as `Error::ListenerFailed`.
+    pub fn try_on<F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self, Option<&V>) -> Result<(), BoxError> + 'static,
+    {
+        self.add_listener(Key::Event(event), Callback::Fallible(Box::new(f)), 0, None)
+    }
+
+    /// Emits an `event` with a `value` associated to it, firing all
+    /// listeners connected to it and gathering the errors of the ones
+    /// added with `try_on`.
+    ///
+    /// Failing listeners don't keep the others from running, unless the bus
+    /// has been told to `set_stop_on_error`, in which case the first failure
+    /// stops the propagation of the event.
+    pub fn try_emit(&self, event: E, value: Option<&V>) -> Result<(), EmitError<E>> {
+        let mut failures = Vec::new();
+        let dispatched = self.dispatch(&event, |l| match l.handler.call(self, &event, value) {
+            Ok(ran) => ran,
+            Err(error) => {
+                failures.push(ListenerFailure {
+                    subscription: l.id,
+                    error,
+                });
+                if self.stops_on_error() {
+                    self.stop_propagation();
+                }
+                true
+            }
+        });
+
+        self.settle(&dispatched?, &event, value);
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(EmitError::Listeners { event, failures })
+        }
+    }
+
+    /// Adds a listener for `event` whose return value is gathered by
+    /// `emit_collect`.
+    ///
+    /// On any other emit its return value is simply dropped.
+    pub fn on_collect<R, F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        R: 'static,
+        F: Fn(&Self, Option<&V>) -> R + 'static,
+    {
+        let callback = Callback::Returning(Box::new(Returns(f, PhantomData)));
+        self.add_listener(Key::Event(event), callback, 0, None)
+    }
+
+    /// Emits an `event` with a `value` associated to it, firing all
+    /// listeners connected to it and returning the values returned by the
+    /// ones added with `on_collect`, in the order they were invoked.
+    ///
+    /// Fails with `Error::ReturnTypeMismatch` if one of them returns
+    /// anything else than an `R`, once all the listeners have been invoked.
+    pub fn emit_collect<R: 'static>(&self, event: E, value: Option<&V>) -> Result<Vec<R>, Error> {
+        let mut results = Vec::<R>::new();
+        let mut mismatch = None;
+        let dispatched = self.dispatch(&event, |l| {
+            match l.handler.call_returning(self, &event, value, &mut results) {
+                Err(error) if error.is::<TypeMismatch>() => {
+                    mismatch.get_or_insert(l.id);
+                    true
+                }
+                result => self.report_failure(l.id, result),
+            }
+        })?;
+        self.settle(&dispatched, &event, value);
+
+        match mismatch {
+            Some(subscription) => Err(Error::ReturnTypeMismatch { subscription }),
+            None => Ok(results),
+        }
+    }
+
+    /// Adds a listener that will call `f` for every event emitted on
+    /// this bus, along with the event itself.
+    ///
+    /// Such listeners are ordered by priority along with the ones
+    /// registered for the specific event being emitted, and have a
+    /// priority of `0`.
+    pub fn on_any<F>(&self, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self, &E, Option<&V>) + 'static,
+    {
+        self.add_listener(Key::Any, Callback::Any(Box::new(f)), 0, None)
+    }
+
+    /// Adds a listener that will call `f` whenever an event of the same
+    /// enum variant as `variant` is fired, whatever data it carries, along
+    /// with the emitted event.
+    ///
+    /// Only the variant of `variant` matters, the data it carries is
+    /// ignored. This is only meaningful if `E` is an enum: every value of
+    /// any other type, like `&str`, is considered to be the same variant.
+    pub fn on_variant<F>(&self, variant: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self, &E, Option<&V>) + 'static,
+    {
+        let key = Key::Variant(mem::discriminant(&variant));
+        self.add_listener(key, Callback::Any(Box::new(f)), 0, None)
+    }
+
+    /// Adds a listener for `event` that will call `f` when said event
+    /// is fired, but only if `predicate` accepts the emitted value.
+    ///
+    /// The predicate is evaluated on every emit of `event`, right before
+    /// the listener would be invoked.
+    pub fn on_filtered<P, F>(&self, event: E, predicate: P, f: F) -> Result<SubscriptionId, Error>
+    where
+        P: Fn(&E, Option<&V>) -> bool + 'static,
+        F: Fn(&Self, Option<&V>) + 'static,
+    {
+        let callback = Callback::Event(Box::new(f)).filtered(Box::new(predicate));
+        self.add_listener(Key::Event(event), callback, 0, None)
+    }
+
+    /// Like `on_any`, but `f` is only called for the events and values
+    /// accepted by `predicate`.
+    pub fn on_any_filtered<P, F>(&self, predicate: P, f: F) -> Result<SubscriptionId, Error>
+    where
+        P: Fn(&E, Option<&V>) -> bool + 'static,
+        F: Fn(&Self, &E, Option<&V>) + 'static,
+    {
+        let callback = Callback::Any(Box::new(f)).filtered(Box::new(predicate));
+        self.add_listener(Key::Any, callback, 0, None)
+    }
+}
+
+impl<E> BusRef<E::Kind, E>
+where
+    E: Event + 'static,
+{
+    /// Adds a listener that will call `f` with the payload of every event
+    /// of the given `variant` fired on this bus.
+    ///
+    /// Events of such variant have to be fired with `emit_event` for the
+    /// listener to receive them.
+    pub fn on_event<T, F>(&self, _variant: T, f: F) -> Result<SubscriptionId, Error>
+    where
+        T: Variant<E> + 'static,
+        F: Fn(&Self, &T::Payload) + 'static,
+    {
+        self.on(T::kind(), move |bus, event| {
+            if let Some(payload) = event.and_then(T::payload) {
+                f(bus, payload)
+            }
+        })
+    }
+
+    /// Emits `event` under its kind, passing it along as the value of the
+    /// emit.
+    pub fn emit_event(&self, event: E) -> Result<(), Error> {
+        self.emit_with_value(event.kind(), Some(&event))
+    }
 }
 
 impl<E, V> EventEmitter<E, V> for BusRef<E, V>
 where
     E: Hash + Eq,
 {
+    type Ref = Self;
+    type Handler = Handler<E, V>;
+
     /// Adds a listener for `event` that will call `f` when said
     /// event is fired.
-    fn on<F>(&self, event: E, f: F) -> Result<(), Error>
+    fn on<F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
     where
-        F: Fn(&Self, Option<&V>) + 'static,
+        F: Fn(&Self, Option<&V>) + IntoHandler<Handler<E, V>>,
     {
-        let boxed_fn = Box::new(f);
-        match self.listeners.try_borrow_mut() {
-            Ok(mut listeners) => {
-                match listeners.get_mut(&event) {
-                    Some(existing_event) => {
-                        existing_event.push(boxed_fn);
-                    }
-                    None => {
-                        let v: Vec<Box<dyn Fn(&Self, Option<&V>) + 'static>> = vec![boxed_fn];
-                        listeners.insert(event, v);
-                    }
-                }
+        let callback = Callback::Event(f.into_handler());
+        self.add_listener(Key::Event(event), callback, 0, None)
+    }
 
-                Ok(())
-            },
-            Err(_) => Err(Error::BusLock)
-        }
+    /// Adds a listener for `event` that will call `f` when said
+    /// event is fired, before any listener with a lower `priority`.
+    fn on_with_priority<F>(&self, event: E, priority: i32, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self, Option<&V>) + IntoHandler<Handler<E, V>>,
+    {
+        let callback = Callback::Event(f.into_handler());
+        self.add_listener(Key::Event(event), callback, priority, None)
+    }
+
+    /// Adds a listener for `event` that will call `f` the first `n`
+    /// times said event is fired, and is removed afterwards.
+    fn on_n<F>(&self, event: E, n: usize, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self, Option<&V>) + IntoHandler<Handler<E, V>>,
+    {
+        let callback = Callback::Event(f.into_handler());
+        self.add_listener(Key::Event(event), callback, 0, Some(n))
+    }
+
+    /// Removes the listener identified by `subscription`, if it is
+    /// still registered on this bus.
+    fn off(&self, subscription: SubscriptionId) -> Result<bool, Error> {
+        self.remove_listener(subscription)
     }
 
     /// Emits an `event`, firing all listeners connected to it via `on`.
@@ -107,21 +1164,12 @@ where
     /// Emits an `event` with a `value` associated to it,
     /// firing all listeners connected to it via `on`.
     fn emit_with_value(&self, event: E, value: Option<&V>) -> Result<(), Error> {
-        if self.disconnected() {
-            Err(Error::Disconnected)
-        } else {
-            let event_count = self.emit_count.get();
-            self.emit_count.set(event_count + 1);
-            let listeners = self.listeners.borrow();
-
-            match listeners.get(&event) {
-                Some(listeners_fns) => {
-                    let _results = listeners_fns.iter().map(|l| l(&self, value)).collect::<()>();
-                    Ok(())
-                }
-                None => Ok(()),
-            }
-        }
+        self.dispatch(&event, |l| {
+            self.report_failure(l.id, l.handler.call(self, &event, value))
+        })
+        .map(|dispatched| {
+            self.settle(&dispatched, &event, value);
+        })
     }
 }
 
@@ -1,17 +1,921 @@
-use std::{hash::Hash, sync::Arc};
+use std::{
+    any::{Any, TypeId},
+    cell::Cell,
+    collections::HashMap,
+    hash::Hash,
+    marker::PhantomData,
+    mem,
+    ops::ControlFlow,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, RwLock, Weak,
+    },
+};
 
-use crate::prelude::{BusRef, Error, EventEmitter};
+use crate::{
+    limit::{Admission, EmitLimit, Total, Unlimited},
+    listener::{
+        self, ByPriority, Dispatched, Handlers, Key, Listener, Registry, Returning, Returns,
+        TypeMismatch,
+    },
+    prelude::{
+        BoxError, EmitError, Error, Event, EventEmitter, IntoHandler, ListenerFailure,
+        SubscriptionId, Variant,
+    },
+    topic::{split_topic, Pattern, Trie},
+};
+
+/// A listener as stored by a sync `BusRef`
+pub type Handler<E, V> = dyn Fn(&BusRef<E, V>, Option<&V>) + Send + Sync;
+
+impl<E, V, F> IntoHandler<Handler<E, V>> for F
+where
+    F: Fn(&BusRef<E, V>, Option<&V>) + Send + Sync + 'static,
+{
+    fn into_handler(self) -> Box<Handler<E, V>> {
+        Box::new(self)
+    }
+}
+
+/// A listener receiving every event emitted on a sync `BusRef`
+type AnyHandler<E, V> = dyn Fn(&BusRef<E, V>, &E, Option<&V>) + Send + Sync;
+
+/// A listener allowed to modify the value of an `emit_mut`
+type MutHandler<E, V> = dyn Fn(&BusRef<E, V>, &mut V) + Send + Sync;
+
+/// A listener that can fail
+type FallibleHandler<E, V> =
+    dyn Fn(&BusRef<E, V>, Option<&V>) -> Result<(), BoxError> + Send + Sync;
+
+/// A hook receiving the errors that can't be returned to an emitter
+type ErrorHook = dyn Fn(&Error) + Send + Sync;
+
+/// A hook of `BusRef::on_close`
+type CloseHook<E, V> = dyn Fn(&BusRef<E, V>) + Send + Sync;
+
+/// The hook of `BusRef::on_max_listeners`
+type MaxListenersHook<E, V> = dyn Fn(&BusRef<E, V>, &E, usize) + Send + Sync;
+
+/// A predicate deciding whether a listener should receive an emit
+type Filter<E, V> = dyn Fn(&E, Option<&V>) -> bool + Send + Sync;
+
+/// The listeners a sync `BusRef` can invoke
+type Callback<E, V> = listener::Callback<E, V, BusRef<E, V>>;
+
+impl<E, V> Handlers<E, V> for BusRef<E, V> {
+    type Event = Arc<Handler<E, V>>;
+    type Any = Arc<AnyHandler<E, V>>;
+    type Mut = Arc<MutHandler<E, V>>;
+    type Fallible = Arc<FallibleHandler<E, V>>;
+    type Returning = Arc<dyn Returning<Self, V> + Send + Sync>;
+    type Filter = Arc<Filter<E, V>>;
+}
+
+/// An immutable snapshot of the listeners registered for an event
+type Snapshot<E, V> = Arc<Vec<Listener<Callback<E, V>>>>;
+
+/// The snapshots of the listeners for a specific event and for its variant,
+/// if any, and of the ones for any event
+type Snapshots<E, V> = (
+    Option<Snapshot<E, V>>,
+    Option<Snapshot<E, V>>,
+    Snapshot<E, V>,
+);
+
+thread_local! {
+    /// The bus whose emit is the innermost in progress on this thread, by
+    /// address, and whether one of its listeners stopped its propagation.
+    ///
+    /// Emits can run concurrently on different threads, so this can't be
+    /// kept on the bus itself.
+    static PROPAGATION: Cell<(usize, bool)> = const { Cell::new((0, false)) };
+}
+
+/// Marks an emit in progress on this thread in `PROPAGATION`, restoring the
+/// emit it interrupted when dropped even if one of the listeners panics.
+struct Propagation {
+    outer: (usize, bool),
+}
+
+impl Propagation {
+    /// Whether a listener stopped the propagation of the emit
+    fn stopped(&self) -> bool {
+        PROPAGATION.get().1
+    }
+}
+
+impl Drop for Propagation {
+    fn drop(&mut self) {
+        PROPAGATION.set(self.outer);
+    }
+}
+
+/// Inner implementation of a thread safe bus structure.
+///
+/// Listeners for each event are kept in a copy-on-write snapshot: emitters
+/// only hold the table lock long enough to clone the snapshot `Arc`, while
+/// registrations build an updated copy of it and only take the table lock
+/// to swap it in. Listeners are never invoked
+/// while a lock is held, so they are free to register other listeners or
+/// re-emit events on the bus they receive. Listeners added while an event
+/// is being dispatched only see subsequent events.
+pub struct BusRef<E, V> {
+    listeners: RwLock<Registry<E, Snapshot<E, V>>>,
+    registration: Mutex<()>,
+    next_id: AtomicUsize,
+    stop_on_error: AtomicBool,
+    catch_panics: AtomicBool,
+    error_hook: RwLock<Option<Arc<ErrorHook>>>,
+    close_hooks: RwLock<Vec<Box<CloseHook<E, V>>>>,
+    closed: AtomicBool,
+    max_listeners: RwLock<HashMap<E, usize>>,
+    strict_max_listeners: AtomicBool,
+    max_listeners_hook: RwLock<Option<Arc<MaxListenersHook<E, V>>>>,
+    dead_letter: RwLock<Option<Arc<AnyHandler<E, V>>>>,
+    unhandled_count: AtomicUsize,
+    emit_count: AtomicUsize,
+    emit_limit: Box<dyn EmitLimit<E> + Send + Sync>,
+}
+
+impl<E, V> BusRef<E, V> {
+    pub(crate) fn unbound() -> Self {
+        Self::with_limit(Box::new(Unlimited))
+    }
+
+    pub(crate) fn bound(max_emit_count: usize) -> Self {
+        Self::with_limit(Box::new(Total::new(max_emit_count)))
+    }
+
+    pub(crate) fn with_limit(emit_limit: Box<dyn EmitLimit<E> + Send + Sync>) -> Self {
+        Self {
+            listeners: RwLock::new(Registry::new()),
+            registration: Mutex::new(()),
+            next_id: AtomicUsize::new(0),
+            stop_on_error: AtomicBool::new(false),
+            catch_panics: AtomicBool::new(false),
+            error_hook: RwLock::new(None),
+            dead_letter: RwLock::new(None),
+            close_hooks: RwLock::new(Vec::new()),
+            closed: AtomicBool::new(false),
+            max_listeners: RwLock::new(HashMap::new()),
+            strict_max_listeners: AtomicBool::new(false),
+            max_listeners_hook: RwLock::new(None),
+            unhandled_count: AtomicUsize::new(0),
+            emit_count: AtomicUsize::new(0),
+            emit_limit,
+        }
+    }
+
+    pub fn disconnected(&self) -> bool {
+        self.emit_limit.disconnected()
+    }
+
+    pub fn event_count(&self) -> usize {
+        self.emit_count.load(Ordering::SeqCst)
+    }
+
+    fn next_subscription(&self) -> SubscriptionId {
+        SubscriptionId::new(self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// The current snapshots of the listeners for `event`, for its variant
+    /// and of the ones for any event
+    fn snapshot(&self, event: &E) -> Result<Snapshots<E, V>, Error>
+    where
+        E: Hash + Eq,
+    {
+        let listeners = self.listeners.read().map_err(|_| Error::Poisoned)?;
+        if self.is_closed() {
+            return Err(Error::Closed);
+        }
+
+        Ok((
+            listeners.event(event).cloned(),
+            listeners.variant(event).cloned(),
+            Arc::clone(listeners.any()),
+        ))
+    }
+
+    /// Returns `true` if this bus has been closed with `close`
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Closes this bus, after which adding listeners or emitting events on
+    /// it fails with `Error::Closed`, then runs the hooks set with
+    /// `on_close`, in the order they were added.
+    ///
+    /// The listeners and hooks of the bus are dropped along the way, while
+    /// the emits in progress finish with the listeners they started with.
+    /// Closing a bus that was already closed does nothing.
+    pub fn close(&self) {
+        // Closing under the table lock, which registrations check it under,
+        // keeps listeners from being added once the table has been cleared
+        let mut listeners = self
+            .listeners
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        listeners.clear();
+        drop(listeners);
+
+        if let Ok(mut hook) = self.error_hook.write() {
+            hook.take();
+        }
+        if let Ok(mut handler) = self.dead_letter.write() {
+            handler.take();
+        }
+        if let Ok(mut hook) = self.max_listeners_hook.write() {
+            hook.take();
+        }
+
+        let hooks = match self.close_hooks.write() {
+            Ok(mut hooks) => mem::take(&mut *hooks),
+            Err(_) => return,
+        };
+        for hook in hooks {
+            hook(self);
+        }
+    }
+
+    /// Adds a hook that will call `f` once this bus gets closed.
+    pub fn on_close<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: Fn(&Self) + Send + Sync + 'static,
+    {
+        // Checked under the lock `close` takes the hooks under, so that a
+        // hook is either run or refused
+        let mut hooks = self.close_hooks.write().map_err(|_| Error::Poisoned)?;
+        if self.is_closed() {
+            return Err(Error::Closed);
+        }
+
+        hooks.push(Box::new(f));
+        Ok(())
+    }
+
+    /// Resets the count of events emitted on this bus along with its limit
+    /// policy, so that a bus that reached its bounds can emit again.
+    pub fn reset(&self) -> Result<(), Error> {
+        if self.is_closed() {
+            return Err(Error::Closed);
+        }
+
+        self.emit_limit.reset();
+        self.emit_count.store(0, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Sets whether `try_emit` stops at the first listener that fails,
+    /// rather than running every listener and reporting all failures.
+    pub fn set_stop_on_error(&self, stop: bool) {
+        self.stop_on_error.store(stop, Ordering::Relaxed);
+    }
+
+    fn stops_on_error(&self) -> bool {
+        self.stop_on_error.load(Ordering::Relaxed)
+    }
+
+    /// Sets whether panics of the listeners of this bus are caught, so that
+    /// the listeners after them still run and the emit completes normally.
+    ///
+    /// Caught panics are reported to the hook set with `on_error` as an
+    /// `Error::ListenerPanicked`. Note that the panic hook of the process
+    /// still runs when a listener panics.
+    pub fn set_catch_panics(&self, catch: bool) {
+        self.catch_panics.store(catch, Ordering::Relaxed);
+    }
+
+    /// Sets the hook receiving the errors this bus can't return to an
+    /// emitter, replacing the previous one.
+    pub fn on_error<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: Fn(&Error) + Send + Sync + 'static,
+    {
+        let mut hook = self.error_hook.write().map_err(|_| Error::Poisoned)?;
+        *hook = Some(Arc::new(f));
+        Ok(())
+    }
+
+    /// Sets the handler receiving the events emitted while no listener
+    /// was there to receive them, along with their value, replacing the
+    /// previous one.
+    ///
+    /// Listeners skipping an emit, like a filtered one whose predicate
+    /// rejects it or one added with `on_mut` on any other emit than
+    /// `emit_mut`, don't count as receiving it.
+    pub fn on_unhandled<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: Fn(&Self, &E, Option<&V>) + Send + Sync + 'static,
+    {
+        let mut handler = self.dead_letter.write().map_err(|_| Error::Poisoned)?;
+        *handler = Some(Arc::new(f));
+        Ok(())
+    }
+
+    /// Sets whether adding a listener that brings an event over its max
+    /// number of listeners fails with `Error::TooManyListeners`, rather
+    /// than warning the hook set with `on_max_listeners`.
+    pub fn set_strict_max_listeners(&self, strict: bool) {
+        self.strict_max_listeners.store(strict, Ordering::Relaxed);
+    }
+
+    /// Sets the hook warned when an event gets more listeners than its max
+    /// number of listeners, along with their count, replacing the previous
+    /// one.
+    ///
+    /// The hook is warned when the count goes over the max, not for every
+    /// listener added past it.
+    pub fn on_max_listeners<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: Fn(&Self, &E, usize) + Send + Sync + 'static,
+    {
+        let mut hook = self
+            .max_listeners_hook
+            .write()
+            .map_err(|_| Error::Poisoned)?;
+        *hook = Some(Arc::new(f));
+        Ok(())
+    }
+
+    /// The number of events emitted on this bus that no listener received
+    pub fn unhandled_count(&self) -> usize {
+        self.unhandled_count.load(Ordering::SeqCst)
+    }
+
+    /// Counts `event` as unhandled and hands it to the dead letter handler
+    fn dead_letter(&self, event: &E, value: Option<&V>) {
+        self.unhandled_count.fetch_add(1, Ordering::SeqCst);
+        let handler = match self.dead_letter.read() {
+            Ok(handler) => handler.clone(),
+            Err(_) => return,
+        };
+        if let Some(handler) = handler {
+            handler(self, event, value);
+        }
+    }
+
+    /// Reports the failure of the listener `subscription`, if it failed,
+    /// returning whether it ran
+    fn report_failure(&self, subscription: SubscriptionId, result: Result<bool, BoxError>) -> bool {
+        match result {
+            Ok(ran) => ran,
+            Err(error) => {
+                self.report(Error::ListenerFailed {
+                    subscription,
+                    message: error.to_string(),
+                });
+                true
+            }
+        }
+    }
+
+    /// Hands `error` to the error hook, if there is one
+    fn report(&self, error: Error) {
+        let hook = match self.error_hook.read() {
+            Ok(hook) => hook.clone(),
+            Err(_) => return,
+        };
+        if let Some(hook) = hook {
+            hook(&error);
+        }
+    }
+
+    /// Stops the event being dispatched from reaching the listeners that
+    /// would run after the current one.
+    ///
+    /// Meant to be called by listeners on the bus they receive: it only
+    /// affects the innermost emit in progress on this bus in the calling
+    /// thread, and has no effect outside of an emit.
+    pub fn stop_propagation(&self) {
+        PROPAGATION.with(|propagation| {
+            let (bus, _) = propagation.get();
+            if bus == self.address() {
+                propagation.set((bus, true));
+            }
+        })
+    }
+
+    /// Identifies this bus in `PROPAGATION`
+    fn address(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    fn begin_propagation(&self) -> Propagation {
+        Propagation {
+            outer: PROPAGATION.replace((self.address(), false)),
+        }
+    }
+
+    /// Removes the listener identified by `subscription`, if it is
+    /// still registered on this bus.
+    pub(crate) fn remove_listener(&self, subscription: SubscriptionId) -> Result<bool, Error> {
+        self.remove_where(|l| l.id == subscription)
+    }
+
+    /// Drops listeners that have used up their invocations
+    fn purge_expired(&self) -> Result<bool, Error> {
+        self.remove_where(|l| l.expired())
+    }
+
+    /// Swaps in new snapshots without the listeners matching `predicate`,
+    /// returning whether any was removed.
+    fn remove_where<P>(&self, predicate: P) -> Result<bool, Error>
+    where
+        P: Fn(&Listener<Callback<E, V>>) -> bool,
+    {
+        let _registration = self.registration.lock().map_err(|_| Error::Poisoned)?;
+        let matching = self
+            .listeners
+            .read()
+            .map_err(|_| Error::Poisoned)?
+            .matching(&predicate);
+        if matching.is_empty() {
+            return Ok(false);
+        }
+
+        let updated = matching
+            .iter()
+            .map(|list| {
+                let kept = list.iter().filter(|l| !predicate(l)).cloned();
+                Arc::new(kept.collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+
+        let mut listeners = self.listeners.write().map_err(|_| Error::Poisoned)?;
+        for (old, new) in matching.iter().zip(updated) {
+            listeners.replace(old, new);
+        }
+        Ok(true)
+    }
+}
+
+impl<E, V> BusRef<E, V>
+where
+    E: Hash + Eq,
+{
+    fn add_listener(
+        &self,
+        key: Key<E>,
+        callback: Callback<E, V>,
+        priority: i32,
+        limit: Option<usize>,
+    ) -> Result<SubscriptionId, Error> {
+        if self.is_closed() {
+            return Err(Error::Closed);
+        }
+
+        if limit == Some(0) {
+            return Err(Error::InvalidLimit);
+        }
+
+        let id = self.next_subscription();
+        let listener = Listener::new(id, callback, priority, limit);
+        let mut warned = false;
+        loop {
+            // Registrations are serialized so that the new snapshot can be
+            // built without holding the table lock, which is only taken for
+            // the swap
+            let registration = self.registration.lock().map_err(|_| Error::Poisoned)?;
+            let current = self
+                .listeners
+                .read()
+                .map_err(|_| Error::Poisoned)?
+                .list(&key)
+                .cloned();
+            let mut list = current
+                .map(|list| list.as_ref().clone())
+                .unwrap_or_default();
+            listener.clone().insert_into(&mut list);
+
+            let mut listeners = self.listeners.write().map_err(|_| Error::Poisoned)?;
+            if self.is_closed() {
+                return Err(Error::Closed);
+            }
+
+            if let Key::Event(event) = &key {
+                let count = listeners.event(event).map_or(0, |l| l.len()) + 1;
+                if self.watch_listeners(event, count)? && !warned {
+                    // The hook runs without any lock held so that it can use
+                    // the bus, then the count is checked again
+                    drop(listeners);
+                    drop(registration);
+                    self.warn_max_listeners(event, count)?;
+                    warned = true;
+                    continue;
+                }
+            }
+
+            listeners.set(key, Arc::new(list));
+            return Ok(id);
+        }
+    }
+
+    /// Sets the max number of listeners of `event`, past which adding
+    /// another one is reported as a likely leak of subscriptions.
+    pub fn set_max_listeners(&self, event: E, max: usize) -> Result<(), Error> {
+        self.max_listeners
+            .write()
+            .map_err(|_| Error::Poisoned)?
+            .insert(event, max);
+        Ok(())
+    }
+
+    /// The number of listeners of `event`, not counting the listeners of
+    /// its variant nor of any event.
+    pub fn listener_count(&self, event: &E) -> usize {
+        self.listeners
+            .read()
+            .map(|listeners| listeners.event(event).map_or(0, |l| l.len()))
+            .unwrap_or(0)
+    }
+
+    /// Checks that `event` can get its `count`th listener, failing in
+    /// strict mode when it can't, and returns whether the hook set with
+    /// `on_max_listeners` should be warned that it goes over the limit.
+    fn watch_listeners(&self, event: &E, count: usize) -> Result<bool, Error> {
+        let limit = match self
+            .max_listeners
+            .read()
+            .map_err(|_| Error::Poisoned)?
+            .get(event)
+        {
+            Some(limit) => *limit,
+            None => return Ok(false),
+        };
+
+        if count <= limit {
+            return Ok(false);
+        }
+
+        if self.strict_max_listeners.load(Ordering::Relaxed) {
+            return Err(Error::TooManyListeners { limit, count });
+        }
+
+        Ok(count == limit + 1)
+    }
+
+    /// Hands `event` and its `count` of listeners to the hook set with
+    /// `on_max_listeners`, if there is one
+    fn warn_max_listeners(&self, event: &E, count: usize) -> Result<(), Error> {
+        let hook = self
+            .max_listeners_hook
+            .read()
+            .map_err(|_| Error::Poisoned)?
+            .clone();
+        if let Some(hook) = hook {
+            hook(self, event, count);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the limit policy of this bus doesn't let it emit
+    /// `event` anymore, while other events may still be emitted.
+    pub fn event_disconnected(&self, event: &E) -> bool {
+        self.emit_limit.event_disconnected(event)
+    }
+
+    /// Invokes the listeners of `event` in order through `invoke`, then
+    /// drops the ones that have expired meanwhile.
+    fn dispatch<F>(&self, event: &E, mut invoke: F) -> Result<Dispatched, Error>
+    where
+        F: FnMut(&Listener<Callback<E, V>>) -> bool,
+    {
+        if self.is_closed() {
+            return Err(Error::Closed);
+        }
+
+        if self.emit_limit.acquire(event)? == Admission::Drop {
+            return Ok(Dispatched::dropped());
+        }
+
+        // The reserved emit is given back if the bus can't go through with it
+        let (for_event, for_variant, any) = self
+            .snapshot(event)
+            .inspect_err(|_| self.emit_limit.release(event))?;
+        self.emit_count.fetch_add(1, Ordering::SeqCst);
+        let lists = [
+            for_event.as_deref().map(Vec::as_slice).unwrap_or_default(),
+            for_variant
+                .as_deref()
+                .map(Vec::as_slice)
+                .unwrap_or_default(),
+            &any,
+        ];
+
+        let propagation = self.begin_propagation();
+        let mut has_expired = false;
+        let mut handled = false;
+        for l in ByPriority::new(lists).filter(|l| l.claim()) {
+            handled |= if self.catch_panics.load(Ordering::Relaxed) {
+                listener::isolate(l.id, || invoke(l)).unwrap_or_else(|error| {
+                    self.report(error);
+                    true
+                })
+            } else {
+                invoke(l)
+            };
+            has_expired |= l.expired();
+            if propagation.stopped() {
+                break;
+            }
+        }
+        let stopped = propagation.stopped();
+        drop(propagation);
+
+        if has_expired {
+            self.purge_expired()?;
+        }
+
+        Ok(Dispatched {
+            stopped,
+            handled,
+            dropped: false,
+        })
+    }
+
+    /// Emits an `event` with a `value` associated to it like
+    /// `emit_with_value` does, reporting whether a listener halted it with
+    /// `stop_propagation` before it reached every listener.
+    pub fn emit_controlled(&self, event: E, value: Option<&V>) -> Result<ControlFlow<()>, Error> {
+        self.dispatch(&event, |l| {
+            self.report_failure(l.id, l.handler.call(self, &event, value))
+        })
+        .map(|dispatched| {
+            self.settle(&dispatched, &event, value);
+            dispatched.flow()
+        })
+    }
+
+    /// Hands `event` over to the dead letter handler if no listener
+    /// received it
+    fn settle(&self, dispatched: &Dispatched, event: &E, value: Option<&V>) {
+        if !dispatched.handled && !dispatched.dropped {
+            self.dead_letter(event, value);
+        }
+    }
+
+    /// Adds a listener for `event` that will call `f` with a mutable
+    /// reference to the value of every `emit_mut` of said event.
+    ///
+    /// Such listeners are not invoked by `emit` and `emit_with_value`.
+    pub fn on_mut<F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self, &mut V) + Send + Sync + 'static,
+    {
+        self.add_listener(Key::Event(event), Callback::Mut(Arc::new(f)), 0, None)
+    }
+
+    /// Emits an `event` with a `value` that listeners added with `on_mut`
+    /// can modify, firing all listeners connected to it.
+    ///
+    /// Listeners run in the usual order, each receiving the value as left
+    /// by the ones before it, so once this returns `value` holds the
+    /// outcome of the whole pipeline.
+    pub fn emit_mut(&self, event: E, value: &mut V) -> Result<(), Error> {
+        self.dispatch(&event, |l| {
+            self.report_failure(l.id, l.handler.call_mut(self, &event, value))
+        })
+        .map(|dispatched| {
+            self.settle(&dispatched, &event, Some(value));
+        })
+    }
+
+    /// Adds a listener for `event` that can fail, whose errors are
+    /// reported by `try_emit`.
+    ///
+    /// On any other emit its errors are reported to the hook set with
+    /// `on_error` as `Error::ListenerFailed`.
+    pub fn try_on<F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self, Option<&V>) -> Result<(), BoxError> + Send + Sync + 'static,
+    {
+        self.add_listener(Key::Event(event), Callback::Fallible(Arc::new(f)), 0, None)
+    }
+
+    /// Emits an `event` with a `value` associated to it, firing all
+    /// listeners connected to it and gathering the errors of the ones
+    /// added with `try_on`.
+    ///
+    /// Failing listeners don't keep the others from running, unless the bus
+    /// has been told to `set_stop_on_error`, in which case the first failure
+    /// stops the propagation of the event.
+    pub fn try_emit(&self, event: E, value: Option<&V>) -> Result<(), EmitError<E>> {
+        let mut failures = Vec::new();
+        let dispatched = self.dispatch(&event, |l| match l.handler.call(self, &event, value) {
+            Ok(ran) => ran,
+            Err(error) => {
+                failures.push(ListenerFailure {
+                    subscription: l.id,
+                    error,
+                });
+                if self.stops_on_error() {
+                    self.stop_propagation();
+                }
+                true
+            }
+        });
+
+        self.settle(&dispatched?, &event, value);
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(EmitError::Listeners { event, failures })
+        }
+    }
+
+    /// Adds a listener for `event` whose return value is gathered by
+    /// `emit_collect`.
+    ///
+    /// On any other emit its return value is simply dropped.
+    pub fn on_collect<R, F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        R: 'static,
+        F: Fn(&Self, Option<&V>) -> R + Send + Sync + 'static,
+    {
+        let callback = Callback::Returning(Arc::new(Returns(f, PhantomData)));
+        self.add_listener(Key::Event(event), callback, 0, None)
+    }
+
+    /// Emits an `event` with a `value` associated to it, firing all
+    /// listeners connected to it and returning the values returned by the
+    /// ones added with `on_collect`, in the order they were invoked.
+    ///
+    /// Fails with `Error::ReturnTypeMismatch` if one of them returns
+    /// anything else than an `R`, once all the listeners have been invoked.
+    pub fn emit_collect<R: 'static>(&self, event: E, value: Option<&V>) -> Result<Vec<R>, Error> {
+        let mut results = Vec::<R>::new();
+        let mut mismatch = None;
+        let dispatched = self.dispatch(&event, |l| {
+            match l.handler.call_returning(self, &event, value, &mut results) {
+                Err(error) if error.is::<TypeMismatch>() => {
+                    mismatch.get_or_insert(l.id);
+                    true
+                }
+                result => self.report_failure(l.id, result),
+            }
+        })?;
+        self.settle(&dispatched, &event, value);
+
+        match mismatch {
+            Some(subscription) => Err(Error::ReturnTypeMismatch { subscription }),
+            None => Ok(results),
+        }
+    }
+
+    /// Adds a listener that will call `f` for every event emitted on
+    /// this bus, along with the event itself.
+    ///
+    /// Such listeners are ordered by priority along with the ones
+    /// registered for the specific event being emitted, and have a
+    /// priority of `0`.
+    pub fn on_any<F>(&self, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self, &E, Option<&V>) + Send + Sync + 'static,
+    {
+        self.add_listener(Key::Any, Callback::Any(Arc::new(f)), 0, None)
+    }
+
+    /// Adds a listener that will call `f` whenever an event of the same
+    /// enum variant as `variant` is fired, whatever data it carries, along
+    /// with the emitted event.
+    ///
+    /// Only the variant of `variant` matters, the data it carries is
+    /// ignored. This is only meaningful if `E` is an enum: every value of
+    /// any other type, like `&str`, is considered to be the same variant.
+    pub fn on_variant<F>(&self, variant: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self, &E, Option<&V>) + Send + Sync + 'static,
+    {
+        let key = Key::Variant(mem::discriminant(&variant));
+        self.add_listener(key, Callback::Any(Arc::new(f)), 0, None)
+    }
+
+    /// Adds a listener for `event` that will call `f` when said event
+    /// is fired, but only if `predicate` accepts the emitted value.
+    ///
+    /// The predicate is evaluated on every emit of `event`, right before
+    /// the listener would be invoked.
+    pub fn on_filtered<P, F>(&self, event: E, predicate: P, f: F) -> Result<SubscriptionId, Error>
+    where
+        P: Fn(&E, Option<&V>) -> bool + Send + Sync + 'static,
+        F: Fn(&Self, Option<&V>) + Send + Sync + 'static,
+    {
+        let callback = Callback::Event(Arc::new(f)).filtered(Arc::new(predicate));
+        self.add_listener(Key::Event(event), callback, 0, None)
+    }
+
+    /// Like `on_any`, but `f` is only called for the events and values
+    /// accepted by `predicate`.
+    pub fn on_any_filtered<P, F>(&self, predicate: P, f: F) -> Result<SubscriptionId, Error>
+    where
+        P: Fn(&E, Option<&V>) -> bool + Send + Sync + 'static,
+        F: Fn(&Self, &E, Option<&V>) + Send + Sync + 'static,
+    {
+        let callback = Callback::Any(Arc::new(f)).filtered(Arc::new(predicate));
+        self.add_listener(Key::Any, callback, 0, None)
+    }
+}
+
+impl<E> BusRef<E::Kind, E>
+where
+    E: Event + 'static,
+{
+    /// Adds a listener that will call `f` with the payload of every event
+    /// of the given `variant` fired on this bus.
+    ///
+    /// Events of such variant have to be fired with `emit_event` for the
+    /// listener to receive them.
+    pub fn on_event<T, F>(&self, _variant: T, f: F) -> Result<SubscriptionId, Error>
+    where
+        T: Variant<E> + 'static,
+        F: Fn(&Self, &T::Payload) + Send + Sync + 'static,
+    {
+        self.on(T::kind(), move |bus, event| {
+            if let Some(payload) = event.and_then(T::payload) {
+                f(bus, payload)
+            }
+        })
+    }
+
+    /// Emits `event` under its kind, passing it along as the value of the
+    /// emit.
+    pub fn emit_event(&self, event: E) -> Result<(), Error> {
+        self.emit_with_value(event.kind(), Some(&event))
+    }
+}
+
+impl<E, V> EventEmitter<E, V> for BusRef<E, V>
+where
+    E: Hash + Eq,
+{
+    type Ref = Self;
+    type Handler = Handler<E, V>;
+
+    /// Adds a listener for `event` that will call `f` when said
+    /// event is fired.
+    fn on<F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self, Option<&V>) + IntoHandler<Handler<E, V>>,
+    {
+        let callback = Callback::Event(Arc::from(f.into_handler()));
+        self.add_listener(Key::Event(event), callback, 0, None)
+    }
+
+    /// Adds a listener for `event` that will call `f` when said
+    /// event is fired, before any listener with a lower `priority`.
+    fn on_with_priority<F>(&self, event: E, priority: i32, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self, Option<&V>) + IntoHandler<Handler<E, V>>,
+    {
+        let callback = Callback::Event(Arc::from(f.into_handler()));
+        self.add_listener(Key::Event(event), callback, priority, None)
+    }
+
+    /// Adds a listener for `event` that will call `f` the first `n`
+    /// times said event is fired, and is removed afterwards.
+    fn on_n<F>(&self, event: E, n: usize, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self, Option<&V>) + IntoHandler<Handler<E, V>>,
+    {
+        let callback = Callback::Event(Arc::from(f.into_handler()));
+        self.add_listener(Key::Event(event), callback, 0, Some(n))
+    }
+
+    /// Removes the listener identified by `subscription`, if it is
+    /// still registered on this bus.
+    fn off(&self, subscription: SubscriptionId) -> Result<bool, Error> {
+        self.remove_listener(subscription)
+    }
+
+    /// Emits an `event` with a `value` associated to it,
+    /// firing all listeners connected to it via `on`.
+    fn emit_with_value(&self, event: E, value: Option<&V>) -> Result<(), Error> {
+        self.dispatch(&event, |l| {
+            self.report_failure(l.id, l.handler.call(self, &event, value))
+        })
+        .map(|dispatched| {
+            self.settle(&dispatched, &event, value);
+        })
+    }
+}
 
 /// An event bus that can be cloned and shared across threads. If you do not
 /// need to share the bus across threads use `unsync::EventBus` which is
 /// more efficient in terms of performance since it doens't need to hold
-/// locks on resources
+/// locks on resources.
+///
+/// Listeners registered on this bus must be `Send + Sync`, since they can be
+/// invoked from whichever thread emits an event.
 ///
 /// # Example
 ///
 /// ```
 /// use tram::{prelude::*, sync::EventBus};
-/// use std::{cell::RefCell, rc::Rc};
+/// use std::sync::{Arc, Mutex};
 ///
 /// #[derive(PartialEq, Eq, Hash)]
 /// enum EventType {
@@ -26,256 +930,2000 @@ use crate::prelude::{BusRef, Error, EventEmitter};
 /// }
 ///
 /// let bus: EventBus<EventType, ()> = EventBus::unbound();
-/// let status = Rc::new(RefCell::new(Status::Stopped));
-/// let status_closure = Rc::clone(&status);
+/// let status = Arc::new(Mutex::new(Status::Stopped));
+/// let status_closure = Arc::clone(&status);
 ///
 /// bus.on(EventType::Start, move |_bus, _| {
-///     *status_closure.borrow_mut() = Status::Started;
+///     *status_closure.lock().unwrap() = Status::Started;
 /// })
 /// .expect("Failed to listen for this event");
 ///
 /// bus.emit(EventType::Start).expect("Failed to emit");
 ///
-/// assert_eq!(*status.borrow(), Status::Started);
+/// assert_eq!(*status.lock().unwrap(), Status::Started);
 /// assert_eq!(bus.event_count(), 1);
 /// ```
 pub struct EventBus<E, V> {
     bus: Arc<BusRef<E, V>>,
 }
 
-impl<E, V> EventBus<E, V> {
-    /// Creates an unbound bus that can emit any number of events
-    pub fn unbound() -> Self {
-        Self {
-            bus: Arc::new(BusRef::unbound()),
+impl<E, V> EventBus<E, V> {
+    /// Creates an unbound bus that can emit any number of events
+    pub fn unbound() -> Self {
+        Self {
+            bus: Arc::new(BusRef::unbound()),
+        }
+    }
+
+    /// Creates a bound bus that can emit up to `limit` events, none at all
+    /// if `limit` is 0
+    pub fn bound(limit: usize) -> Self {
+        Self {
+            bus: Arc::new(BusRef::bound(limit)),
+        }
+    }
+
+    /// Creates a bus whose emits are bound by the `limit` policy, see the
+    /// `limit` module for the available ones.
+    pub fn with_limit<L>(limit: L) -> Self
+    where
+        L: EmitLimit<E> + Send + Sync + 'static,
+    {
+        Self {
+            bus: Arc::new(BusRef::with_limit(Box::new(limit))),
+        }
+    }
+
+    /// Returns `true` if this bus has exausted its allowed max number of emits
+    pub fn disconnected(&self) -> bool {
+        let bus_lock = self.bus_ref();
+        bus_lock.disconnected()
+    }
+
+    fn bus_ref(&self) -> &BusRef<E, V> {
+        self.bus.as_ref()
+    }
+
+    pub fn event_count(&self) -> usize {
+        self.bus_ref().event_count()
+    }
+
+    /// Returns `true` if this bus has been closed with `close`
+    pub fn is_closed(&self) -> bool {
+        self.bus.is_closed()
+    }
+
+    /// Closes this bus, after which adding listeners or emitting events on
+    /// it fails with `Error::Closed`, then runs the hooks set with
+    /// `on_close`, in the order they were added, dropping its listeners
+    /// and hooks.
+    ///
+    /// Closing a bus that was already closed does nothing.
+    pub fn close(&self) {
+        self.bus.close()
+    }
+
+    /// Adds a hook that will call `f` once this bus gets closed.
+    pub fn on_close<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: Fn(&BusRef<E, V>) + Send + Sync + 'static,
+    {
+        self.bus.on_close(f)
+    }
+
+    /// Resets the count of events emitted on this bus along with its limit
+    /// policy, so that a bus that reached its bounds can emit again.
+    pub fn reset(&self) -> Result<(), Error> {
+        self.bus.reset()
+    }
+
+    /// Sets whether `try_emit` stops at the first listener that fails,
+    /// rather than running every listener and reporting all failures.
+    pub fn set_stop_on_error(&self, stop: bool) {
+        self.bus.set_stop_on_error(stop)
+    }
+
+    /// Sets whether panics of the listeners of this bus are caught and
+    /// reported to the hook set with `on_error`, rather than unwinding
+    /// through the emit.
+    pub fn set_catch_panics(&self, catch: bool) {
+        self.bus.set_catch_panics(catch)
+    }
+
+    /// Sets whether adding a listener that brings an event over its max
+    /// number of listeners fails with `Error::TooManyListeners`, rather
+    /// than warning the hook set with `on_max_listeners`.
+    pub fn set_strict_max_listeners(&self, strict: bool) {
+        self.bus.set_strict_max_listeners(strict)
+    }
+
+    /// Sets the hook warned when an event gets more listeners than its max
+    /// number of listeners, along with their count, replacing the previous
+    /// one.
+    pub fn on_max_listeners<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: Fn(&BusRef<E, V>, &E, usize) + Send + Sync + 'static,
+    {
+        self.bus.on_max_listeners(f)
+    }
+
+    /// The number of events emitted on this bus that no listener received
+    pub fn unhandled_count(&self) -> usize {
+        self.bus.unhandled_count()
+    }
+
+    /// Sets the handler receiving the events emitted while no listener
+    /// was there to receive them, along with their value, replacing the
+    /// previous one.
+    pub fn on_unhandled<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: Fn(&BusRef<E, V>, &E, Option<&V>) + Send + Sync + 'static,
+    {
+        self.bus.on_unhandled(f)
+    }
+
+    /// Sets the hook receiving the errors this bus can't return to an
+    /// emitter, replacing the previous one.
+    pub fn on_error<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: Fn(&Error) + Send + Sync + 'static,
+    {
+        self.bus.on_error(f)
+    }
+}
+
+impl<E, V> EventBus<E, V>
+where
+    E: Eq + Hash,
+{
+    /// Sets the max number of listeners of `event`, past which adding
+    /// another one is reported as a likely leak of subscriptions.
+    pub fn set_max_listeners(&self, event: E, max: usize) -> Result<(), Error> {
+        self.bus.set_max_listeners(event, max)
+    }
+
+    /// The number of listeners of `event`, not counting the listeners of
+    /// its variant nor of any event.
+    pub fn listener_count(&self, event: &E) -> usize {
+        self.bus.listener_count(event)
+    }
+
+    /// Returns `true` if the limit policy of this bus doesn't let it emit
+    /// `event` anymore, while other events may still be emitted.
+    pub fn event_disconnected(&self, event: &E) -> bool {
+        self.bus.event_disconnected(event)
+    }
+
+    /// Adds a listener for `event` like `on` does, returning a guard that
+    /// removes it when dropped.
+    pub fn subscribe<F>(&self, event: E, f: F) -> Result<Subscription<E, V>, Error>
+    where
+        F: Fn(&BusRef<E, V>, Option<&V>) + IntoHandler<Handler<E, V>>,
+    {
+        let id = self.bus.on(event, f)?;
+        Ok(Subscription {
+            bus: Arc::downgrade(&self.bus),
+            id,
+        })
+    }
+
+    /// Emits an `event` with a `value` associated to it like
+    /// `emit_with_value` does, reporting whether a listener halted it with
+    /// `BusRef::stop_propagation` before it reached every listener.
+    pub fn emit_controlled(&self, event: E, value: Option<&V>) -> Result<ControlFlow<()>, Error> {
+        self.bus.emit_controlled(event, value)
+    }
+
+    /// Adds a listener for `event` that will call `f` with a mutable
+    /// reference to the value of every `emit_mut` of said event.
+    pub fn on_mut<F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&BusRef<E, V>, &mut V) + Send + Sync + 'static,
+    {
+        self.bus.on_mut(event, f)
+    }
+
+    /// Emits an `event` with a `value` that listeners added with `on_mut`
+    /// can modify. Once this returns `value` holds the outcome of all of
+    /// them.
+    pub fn emit_mut(&self, event: E, value: &mut V) -> Result<(), Error> {
+        self.bus.emit_mut(event, value)
+    }
+
+    /// Adds a listener for `event` that can fail, whose errors are
+    /// reported by `try_emit`.
+    pub fn try_on<F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&BusRef<E, V>, Option<&V>) -> Result<(), BoxError> + Send + Sync + 'static,
+    {
+        self.bus.try_on(event, f)
+    }
+
+    /// Emits an `event` with a `value` associated to it, gathering the
+    /// errors of the listeners added with `try_on`.
+    pub fn try_emit(&self, event: E, value: Option<&V>) -> Result<(), EmitError<E>> {
+        self.bus.try_emit(event, value)
+    }
+
+    /// Adds a listener for `event` whose return value is gathered by
+    /// `emit_collect`.
+    pub fn on_collect<R, F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        R: 'static,
+        F: Fn(&BusRef<E, V>, Option<&V>) -> R + Send + Sync + 'static,
+    {
+        self.bus.on_collect(event, f)
+    }
+
+    /// Emits an `event` with a `value` associated to it, returning the
+    /// values of type `R` returned by the listeners added with `on_collect`,
+    /// in the order they were invoked, or an error if one of them returns
+    /// another type.
+    pub fn emit_collect<R: 'static>(&self, event: E, value: Option<&V>) -> Result<Vec<R>, Error> {
+        self.bus.emit_collect(event, value)
+    }
+
+    /// Adds a listener that will call `f` for every event emitted on this
+    /// bus, along with the event itself.
+    pub fn on_any<F>(&self, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&BusRef<E, V>, &E, Option<&V>) + Send + Sync + 'static,
+    {
+        self.bus.on_any(f)
+    }
+
+    /// Adds a listener that will call `f` whenever an event of the same
+    /// enum variant as `variant` is fired, along with the emitted event.
+    ///
+    /// `E` must be an enum, see [`BusRef::on_variant`].
+    pub fn on_variant<F>(&self, variant: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&BusRef<E, V>, &E, Option<&V>) + Send + Sync + 'static,
+    {
+        self.bus.on_variant(variant, f)
+    }
+
+    /// Adds a listener for `event` that will call `f` when said event is
+    /// fired, but only if `predicate` accepts the emitted value.
+    pub fn on_filtered<P, F>(&self, event: E, predicate: P, f: F) -> Result<SubscriptionId, Error>
+    where
+        P: Fn(&E, Option<&V>) -> bool + Send + Sync + 'static,
+        F: Fn(&BusRef<E, V>, Option<&V>) + Send + Sync + 'static,
+    {
+        self.bus.on_filtered(event, predicate, f)
+    }
+
+    /// Like `on_any`, but `f` is only called for the events and values
+    /// accepted by `predicate`.
+    pub fn on_any_filtered<P, F>(&self, predicate: P, f: F) -> Result<SubscriptionId, Error>
+    where
+        P: Fn(&E, Option<&V>) -> bool + Send + Sync + 'static,
+        F: Fn(&BusRef<E, V>, &E, Option<&V>) + Send + Sync + 'static,
+    {
+        self.bus.on_any_filtered(predicate, f)
+    }
+}
+
+impl<E> EventBus<E::Kind, E>
+where
+    E: Event + 'static,
+{
+    /// Adds a listener that will call `f` with the payload of every event
+    /// of the given `variant` fired on this bus with `emit_event`.
+    pub fn on_event<T, F>(&self, variant: T, f: F) -> Result<SubscriptionId, Error>
+    where
+        T: Variant<E> + 'static,
+        F: Fn(&BusRef<E::Kind, E>, &T::Payload) + Send + Sync + 'static,
+    {
+        self.bus.on_event(variant, f)
+    }
+
+    /// Emits `event` under its kind, passing it along as the value of the
+    /// emit.
+    pub fn emit_event(&self, event: E) -> Result<(), Error> {
+        self.bus.emit_event(event)
+    }
+}
+
+impl<E, V> EventEmitter<E, V> for EventBus<E, V>
+where
+    E: Eq + Hash,
+{
+    type Ref = BusRef<E, V>;
+    type Handler = Handler<E, V>;
+
+    fn on<F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&BusRef<E, V>, Option<&V>) + IntoHandler<Handler<E, V>>,
+    {
+        self.bus_ref().on(event, f)
+    }
+
+    fn on_with_priority<F>(&self, event: E, priority: i32, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&BusRef<E, V>, Option<&V>) + IntoHandler<Handler<E, V>>,
+    {
+        self.bus_ref().on_with_priority(event, priority, f)
+    }
+
+    fn on_n<F>(&self, event: E, n: usize, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&BusRef<E, V>, Option<&V>) + IntoHandler<Handler<E, V>>,
+    {
+        self.bus_ref().on_n(event, n, f)
+    }
+
+    fn off(&self, subscription: SubscriptionId) -> Result<bool, Error> {
+        self.bus_ref().off(subscription)
+    }
+
+    fn emit(&self, event: E) -> Result<(), Error> {
+        self.emit_with_value(event, None)
+    }
+
+    fn emit_with_value(&self, event: E, value: Option<&V>) -> Result<(), Error> {
+        self.bus_ref().emit_with_value(event, value)
+    }
+}
+
+impl<E, V> Clone for EventBus<E, V> {
+    fn clone(&self) -> Self {
+        Self {
+            bus: Arc::clone(&self.bus),
+        }
+    }
+}
+
+/// A guard that removes its listener from the bus when dropped.
+///
+/// It only holds a weak reference to the bus, so it doesn't keep the bus
+/// alive: if the bus is gone by the time the guard is dropped there is
+/// nothing left to do.
+#[must_use = "dropping a `Subscription` immediately removes its listener"]
+pub struct Subscription<E, V> {
+    bus: Weak<BusRef<E, V>>,
+    id: SubscriptionId,
+}
+
+impl<E, V> Subscription<E, V> {
+    /// The id of the guarded listener
+    pub fn id(&self) -> SubscriptionId {
+        self.id
+    }
+
+    /// Releases the guard without removing the listener, returning its id
+    pub fn detach(mut self) -> SubscriptionId {
+        self.bus = Weak::new();
+        self.id
+    }
+}
+
+impl<E, V> Drop for Subscription<E, V> {
+    fn drop(&mut self) {
+        if let Some(bus) = self.bus.upgrade() {
+            let _ = bus.remove_listener(self.id);
+        }
+    }
+}
+
+/// A listener as stored by a `TopicBus`
+type TopicHandler<V> = dyn Fn(&TopicBus<V>, &str, Option<&V>) + Send + Sync;
+
+type TopicListeners<V> = Trie<Listener<Arc<TopicHandler<V>>>>;
+
+struct TopicBusRef<V> {
+    listeners: RwLock<TopicListeners<V>>,
+    next_id: AtomicUsize,
+    emit_count: AtomicUsize,
+}
+
+/// An event bus whose events are hierarchical topics, that can be cloned
+/// and shared across threads.
+///
+/// Listeners subscribe to patterns made of dot separated segments, where `*`
+/// matches exactly one segment and `#` matches zero or more of them (see the
+/// `topic` module). Emitting a topic invokes every listener whose pattern
+/// matches it, in registration order.
+///
+/// # Example
+///
+/// ```
+/// use tram::sync::TopicBus;
+/// use std::sync::{Arc, Mutex};
+///
+/// let bus: TopicBus<u32> = TopicBus::new();
+/// let shipped = Arc::new(Mutex::new(Vec::new()));
+/// let shipped_closure = Arc::clone(&shipped);
+///
+/// bus.on("order.#.shipped", move |_bus, topic, order_id| {
+///     shipped_closure.lock().unwrap().push((topic.to_owned(), *order_id.unwrap()));
+/// })
+/// .expect("Failed to subscribe");
+///
+/// bus.emit_with_value("order.eu.shipped", Some(&1)).expect("Failed to emit");
+/// bus.emit_with_value("order.created", Some(&2)).expect("Failed to emit");
+///
+/// assert_eq!(*shipped.lock().unwrap(), vec![("order.eu.shipped".to_owned(), 1)]);
+/// ```
+pub struct TopicBus<V> {
+    inner: Arc<TopicBusRef<V>>,
+}
+
+impl<V> TopicBus<V> {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(TopicBusRef {
+                listeners: RwLock::new(Trie::new()),
+                next_id: AtomicUsize::new(0),
+                emit_count: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// The current number of topics emitted on this bus
+    pub fn event_count(&self) -> usize {
+        self.inner.emit_count.load(Ordering::SeqCst)
+    }
+
+    /// Adds a listener that will call `f` with the emitted topic and value
+    /// whenever a topic matching `pattern` is emitted.
+    pub fn on<F>(&self, pattern: &str, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self, &str, Option<&V>) + Send + Sync + 'static,
+    {
+        let pattern = Pattern::parse(pattern)?;
+        let id = SubscriptionId::new(self.inner.next_id.fetch_add(1, Ordering::Relaxed));
+        let listener = Listener::new(id, Arc::new(f) as Arc<TopicHandler<V>>, 0, None);
+
+        let mut listeners = self.inner.listeners.write().map_err(|_| Error::Poisoned)?;
+        listeners.insert(&pattern, listener);
+        Ok(id)
+    }
+
+    /// Removes the listener identified by `subscription`.
+    ///
+    /// Returns `false` if no such listener was registered on this bus.
+    pub fn off(&self, subscription: SubscriptionId) -> Result<bool, Error> {
+        let mut listeners = self.inner.listeners.write().map_err(|_| Error::Poisoned)?;
+        Ok(listeners.remove_where(&|l| l.id == subscription))
+    }
+
+    /// Emits `topic`, firing all listeners whose pattern matches it.
+    ///
+    /// When used this way the value passed to listeners will always be `None`.
+    pub fn emit(&self, topic: &str) -> Result<(), Error> {
+        self.emit_with_value(topic, None)
+    }
+
+    /// Emits `topic` with a `value` associated to it, firing all listeners
+    /// whose pattern matches it.
+    ///
+    /// Emitted topics can't contain wildcards. Listeners added or removed
+    /// while the topic is being dispatched don't affect this emit.
+    pub fn emit_with_value(&self, topic: &str, value: Option<&V>) -> Result<(), Error> {
+        let segments = split_topic(topic)?;
+
+        let mut handlers = {
+            let listeners = self.inner.listeners.read().map_err(|_| Error::Poisoned)?;
+            listeners
+                .matches(&segments)
+                .into_iter()
+                .map(|l| (l.id, Arc::clone(&l.handler)))
+                .collect::<Vec<_>>()
+        };
+        handlers.sort_by_key(|(id, _)| *id);
+
+        self.inner.emit_count.fetch_add(1, Ordering::SeqCst);
+        handlers
+            .iter()
+            .for_each(|(_, handler)| handler(self, topic, value));
+
+        Ok(())
+    }
+}
+
+impl<V> Default for TopicBus<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Clone for TopicBus<V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+/// A listener as stored by a `TypedBus`, receiving the emitted event as a
+/// `dyn Any` it downcasts to the type it was registered for
+type TypedHandler = dyn Fn(&TypedBus, &dyn Any) + Send + Sync;
+
+type TypedListeners = HashMap<TypeId, Vec<Listener<Arc<TypedHandler>>>>;
+
+struct TypedBusRef {
+    listeners: RwLock<TypedListeners>,
+    next_id: AtomicUsize,
+    emit_count: AtomicUsize,
+}
+
+/// An event bus where each event is its own Rust type, that can be cloned
+/// and shared across threads.
+///
+/// Listeners are registered for an event type `T` and receive a `&T` when a
+/// value of that type is emitted, so each event carries its own payload
+/// instead of sharing a single value type with every other event. Listeners
+/// for the same type are invoked in registration order.
+///
+/// # Example
+///
+/// ```
+/// use tram::sync::TypedBus;
+/// use std::sync::{Arc, Mutex};
+///
+/// struct UserLoggedIn {
+///     name: String,
+/// }
+///
+/// struct Shutdown;
+///
+/// let bus = TypedBus::new();
+/// let log = Arc::new(Mutex::new(Vec::new()));
+///
+/// let log_closure = Arc::clone(&log);
+/// bus.on(move |_bus, event: &UserLoggedIn| {
+///     log_closure.lock().unwrap().push(format!("{} logged in", event.name));
+/// })
+/// .expect("Failed to listen for this event");
+///
+/// let log_closure = Arc::clone(&log);
+/// bus.on(move |_bus, _: &Shutdown| {
+///     log_closure.lock().unwrap().push("shutdown".to_owned());
+/// })
+/// .expect("Failed to listen for this event");
+///
+/// bus.emit(UserLoggedIn { name: "ada".to_owned() }).expect("Failed to emit");
+/// bus.emit(Shutdown).expect("Failed to emit");
+///
+/// assert_eq!(*log.lock().unwrap(), vec!["ada logged in", "shutdown"]);
+/// ```
+pub struct TypedBus {
+    inner: Arc<TypedBusRef>,
+}
+
+impl TypedBus {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(TypedBusRef {
+                listeners: RwLock::new(HashMap::new()),
+                next_id: AtomicUsize::new(0),
+                emit_count: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// The current number of events emitted on this bus
+    pub fn event_count(&self) -> usize {
+        self.inner.emit_count.load(Ordering::SeqCst)
+    }
+
+    /// Adds a listener that will call `f` whenever an event of type `T` is
+    /// emitted.
+    pub fn on<T, F>(&self, f: F) -> Result<SubscriptionId, Error>
+    where
+        T: 'static,
+        F: Fn(&Self, &T) + Send + Sync + 'static,
+    {
+        let id = SubscriptionId::new(self.inner.next_id.fetch_add(1, Ordering::Relaxed));
+        let handler = Arc::new(move |bus: &Self, event: &dyn Any| {
+            if let Some(event) = event.downcast_ref::<T>() {
+                f(bus, event)
+            }
+        });
+        let listener = Listener::new(id, handler as Arc<TypedHandler>, 0, None);
+
+        let mut listeners = self.inner.listeners.write().map_err(|_| Error::Poisoned)?;
+        listeners
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .push(listener);
+        Ok(id)
+    }
+
+    /// Removes the listener identified by `subscription`.
+    ///
+    /// Returns `false` if no such listener was registered on this bus.
+    pub fn off(&self, subscription: SubscriptionId) -> Result<bool, Error> {
+        let mut listeners = self.inner.listeners.write().map_err(|_| Error::Poisoned)?;
+        let mut removed = false;
+        listeners.retain(|_, list| {
+            let len = list.len();
+            list.retain(|l| l.id != subscription);
+            removed |= list.len() != len;
+            !list.is_empty()
+        });
+        Ok(removed)
+    }
+
+    /// Emits `event`, firing all listeners registered for its type.
+    ///
+    /// Listeners added or removed while the event is being dispatched don't
+    /// affect this emit.
+    pub fn emit<T: 'static>(&self, event: T) -> Result<(), Error> {
+        let handlers = {
+            let listeners = self.inner.listeners.read().map_err(|_| Error::Poisoned)?;
+            listeners
+                .get(&TypeId::of::<T>())
+                .map(|list| {
+                    list.iter()
+                        .map(|l| Arc::clone(&l.handler))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+
+        self.inner.emit_count.fetch_add(1, Ordering::SeqCst);
+        handlers.iter().for_each(|handler| handler(self, &event));
+
+        Ok(())
+    }
+}
+
+impl Default for TypedBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for TypedBus {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{
+        panic::{self, AssertUnwindSafe},
+        sync::Mutex,
+        time::Duration,
+    };
+
+    use crate::limit::{
+        Admission, Clock, ManualClock, Overflow, PerEvent, RateLimit, SlidingWindow, Total,
+    };
+
+    #[derive(PartialEq, Eq, Hash)]
+    enum EventType {
+        Start,
+        Stop,
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Status {
+        Stopped,
+        Started,
+    }
+
+    #[test]
+    fn create_bus() {
+        let _bus: EventBus<u8, ()> = EventBus::unbound();
+    }
+
+    #[test]
+    fn listen_emit_api() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let status = Arc::new(Mutex::new(Status::Stopped));
+        let status_closure = Arc::clone(&status);
+        let status_closure_2 = Arc::clone(&status);
+        bus.on(EventType::Start, move |_, _| {
+            *status_closure.lock().unwrap() = Status::Started;
+        })
+        .unwrap();
+
+        bus.on(EventType::Stop, move |_, _| {
+            *status_closure_2.lock().unwrap() = Status::Stopped;
+        })
+        .unwrap();
+
+        bus.emit(EventType::Start)
+            .expect("Failed to emit START event");
+
+        assert_eq!(*status.lock().unwrap(), Status::Started);
+        assert_eq!(bus.event_count(), 1);
+
+        bus.emit(EventType::Stop)
+            .expect("Failed to emit STOP event");
+
+        assert_eq!(*status.lock().unwrap(), Status::Stopped);
+        assert_eq!(bus.event_count(), 2);
+    }
+
+    #[test]
+    fn listen_emit_api_repeat() {
+        let bus: EventBus<u8, ()> = EventBus::unbound();
+        let status = Arc::new(Mutex::new(0));
+        let status2 = Arc::clone(&status);
+        bus.on(1u8, move |_, _| {
+            *status2.lock().unwrap() += 1;
+        })
+        .unwrap();
+
+        for _ in 0..4 {
+            bus.emit(1).expect("Failed to emit");
+        }
+
+        assert_eq!(*status.lock().unwrap(), 4);
+        assert_eq!(bus.event_count(), 4);
+    }
+
+    #[test]
+    fn threaded_on() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let bus_clone = bus.clone();
+        let status = Arc::new(Mutex::new(Status::Stopped));
+        let final_status = Arc::clone(&status);
+
+        let t1 = std::thread::spawn(move || {
+            bus_clone
+                .on(EventType::Start, move |_, _| {
+                    let mut status_lock = status.lock().unwrap();
+                    *status_lock = Status::Started;
+                })
+                .unwrap();
+        });
+
+        t1.join().unwrap();
+
+        bus.emit(EventType::Start).unwrap();
+
+        let final_status_lock = final_status.lock().unwrap();
+        assert_eq!(*final_status_lock, Status::Started)
+    }
+
+    #[test]
+    fn threaded_emit() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let bus_clone = bus.clone();
+        let status = Arc::new(Mutex::new(Status::Stopped));
+        let final_status = Arc::clone(&status);
+
+        let t1 = std::thread::spawn(move || {
+            bus.emit(EventType::Start).unwrap();
+        });
+
+        bus_clone
+            .on(EventType::Start, move |_, _| {
+                let mut status_lock = status.lock().unwrap();
+                *status_lock = Status::Started;
+            })
+            .unwrap();
+
+        t1.join().unwrap();
+
+        let final_status_lock = final_status.lock().unwrap();
+        assert_eq!(*final_status_lock, Status::Started)
+    }
+
+    #[test]
+    fn with_data() {
+        let bus: EventBus<EventType, u8> = EventBus::unbound();
+        let status: Arc<Mutex<Option<u8>>> = Arc::new(Mutex::new(None));
+        let status_closure = Arc::clone(&status);
+
+        bus.on(EventType::Start, move |_, startup_data: Option<&u8>| {
+            *status_closure.lock().unwrap() = Some(*startup_data.unwrap());
+        })
+        .unwrap();
+
+        bus.emit_with_value(EventType::Start, Some(&123))
+            .expect("Failed to emit");
+
+        assert_eq!(*status.lock().unwrap(), Some(123));
+        assert_eq!(bus.event_count(), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn exceed_bounds() {
+        let bus: EventBus<EventType, u8> = EventBus::bound(5);
+        for i in 0..10 {
+            bus.emit_with_value(EventType::Start, Some(&i))
+                .expect("Failed to emit");
+        }
+    }
+
+    #[test]
+    fn re_emit() {
+        let bus: EventBus<EventType, u8> = EventBus::unbound();
+        // let bus_2: EventBus<EventType, u8> = bus.clone();
+        let status: Arc<Mutex<Option<u8>>> = Arc::new(Mutex::new(None));
+        let status_closure = Arc::clone(&status);
+        let status_closure_2 = Arc::clone(&status);
+
+        bus.on(EventType::Start, move |inner_bus, startup_data| {
+            *status_closure.lock().unwrap() = Some(*startup_data.unwrap());
+            inner_bus
+                .emit(EventType::Stop)
+                .expect("Cannot emit STOP event");
+        })
+        .unwrap();
+
+        bus.on(EventType::Stop, move |_, _| {
+            *status_closure_2.lock().unwrap() = None;
+        })
+        .unwrap();
+
+        bus.emit_with_value(EventType::Start, Some(&123))
+            .expect("Failed to emit");
+
+        assert_eq!(*status.lock().unwrap(), None);
+        assert_eq!(bus.event_count(), 2);
+    }
+
+    #[test]
+    fn contended_on_emit() {
+        let bus: EventBus<u8, ()> = EventBus::unbound();
+        let hits = Arc::new(AtomicUsize::new(0));
+
+        let threads = (0..8)
+            .map(|_| {
+                let bus = bus.clone();
+                let hits = Arc::clone(&hits);
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        let hits = Arc::clone(&hits);
+                        bus.on(1, move |_, _| {
+                            hits.fetch_add(1, Ordering::SeqCst);
+                        })
+                        .unwrap();
+                        bus.emit(2).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        threads.into_iter().for_each(|t| t.join().unwrap());
+
+        bus.emit(1).unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 800);
+        assert_eq!(bus.event_count(), 801);
+    }
+
+    #[test]
+    fn on_inside_listener() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let status = Arc::new(Mutex::new(Status::Started));
+        let status_closure = Arc::clone(&status);
+
+        bus.on(EventType::Start, move |inner_bus, _| {
+            let status_closure = Arc::clone(&status_closure);
+            inner_bus
+                .on(EventType::Stop, move |_, _| {
+                    *status_closure.lock().unwrap() = Status::Stopped;
+                })
+                .expect("Cannot register STOP listener");
+        })
+        .unwrap();
+
+        bus.emit(EventType::Start).unwrap();
+        bus.emit(EventType::Stop).unwrap();
+
+        assert_eq!(*status.lock().unwrap(), Status::Stopped);
+    }
+
+    #[test]
+    fn on_during_emit() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let hits = Arc::new(AtomicUsize::new(0));
+        let hits_closure = Arc::clone(&hits);
+
+        bus.on(EventType::Start, move |inner_bus, _| {
+            hits_closure.fetch_add(1, Ordering::SeqCst);
+            let hits_closure = Arc::clone(&hits_closure);
+            inner_bus
+                .on(EventType::Start, move |_, _| {
+                    hits_closure.fetch_add(1, Ordering::SeqCst);
+                })
+                .unwrap();
+        })
+        .unwrap();
+
+        bus.emit(EventType::Start).unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        bus.emit(EventType::Start).unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn off() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let counter = Arc::new(Mutex::new(0));
+        let counter_closure = Arc::clone(&counter);
+        let counter_closure_2 = Arc::clone(&counter);
+
+        let subscription = bus
+            .on(EventType::Start, move |_, _| {
+                *counter_closure.lock().unwrap() += 1;
+            })
+            .unwrap();
+
+        bus.on(EventType::Start, move |_, _| {
+            *counter_closure_2.lock().unwrap() += 1;
+        })
+        .unwrap();
+
+        assert!(bus.off(subscription).unwrap());
+        assert!(!bus.off(subscription).unwrap());
+
+        bus.emit(EventType::Start).expect("Failed to emit");
+        assert_eq!(*counter.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn subscription_guard() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let counter = Arc::new(Mutex::new(0));
+        let counter_closure = Arc::clone(&counter);
+
+        let subscription = bus
+            .subscribe(EventType::Start, move |_, _| {
+                *counter_closure.lock().unwrap() += 1;
+            })
+            .unwrap();
+
+        bus.emit(EventType::Start).expect("Failed to emit");
+        drop(subscription);
+        bus.emit(EventType::Start).expect("Failed to emit");
+
+        assert_eq!(*counter.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn subscription_detach() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let counter = Arc::new(Mutex::new(0));
+        let counter_closure = Arc::clone(&counter);
+
+        let subscription = bus
+            .subscribe(EventType::Start, move |_, _| {
+                *counter_closure.lock().unwrap() += 1;
+            })
+            .unwrap();
+
+        let id = subscription.detach();
+        bus.emit(EventType::Start).expect("Failed to emit");
+        assert_eq!(*counter.lock().unwrap(), 1);
+
+        assert!(bus.off(id).unwrap());
+    }
+
+    #[test]
+    fn subscription_outlives_bus() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let subscription = bus.subscribe(EventType::Start, |_, _| {}).unwrap();
+        let weak_bus = Arc::downgrade(&bus.bus);
+
+        drop(bus);
+        assert!(weak_bus.upgrade().is_none());
+        drop(subscription);
+    }
+
+    #[test]
+    fn once() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let counter = Arc::new(Mutex::new(0));
+        let counter_closure = Arc::clone(&counter);
+
+        let subscription = bus
+            .once(EventType::Start, move |_, _| {
+                *counter_closure.lock().unwrap() += 1;
+            })
+            .unwrap();
+
+        bus.emit(EventType::Start).expect("Failed to emit");
+        bus.emit(EventType::Start).expect("Failed to emit");
+
+        assert_eq!(*counter.lock().unwrap(), 1);
+        assert!(!bus.off(subscription).unwrap());
+    }
+
+    #[test]
+    fn on_n() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let counter = Arc::new(Mutex::new(0));
+        let counter_closure = Arc::clone(&counter);
+
+        bus.on_n(EventType::Start, 3, move |_, _| {
+            *counter_closure.lock().unwrap() += 1;
+        })
+        .unwrap();
+
+        for _ in 0..5 {
+            bus.emit(EventType::Start).expect("Failed to emit");
+        }
+        assert_eq!(
+            bus.on_n(EventType::Start, 0, |_, _| {}),
+            Err(Error::InvalidLimit)
+        );
+
+        assert_eq!(*counter.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn once_re_emit() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let counter = Arc::new(Mutex::new(0));
+        let counter_closure = Arc::clone(&counter);
+
+        let subscription = bus
+            .once(EventType::Start, move |inner_bus, _| {
+                *counter_closure.lock().unwrap() += 1;
+                inner_bus
+                    .emit(EventType::Start)
+                    .expect("Cannot emit START event");
+            })
+            .unwrap();
+
+        bus.emit(EventType::Start).expect("Failed to emit");
+
+        assert_eq!(*counter.lock().unwrap(), 1);
+        assert_eq!(bus.event_count(), 2);
+        assert!(!bus.off(subscription).unwrap());
+    }
+
+    #[test]
+    fn priorities() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        for (priority, tag) in [
+            (0, "business"),
+            (10, "audit"),
+            (0, "business 2"),
+            (20, "validation"),
+        ] {
+            let order_closure = Arc::clone(&order);
+            bus.on_with_priority(EventType::Start, priority, move |_, _| {
+                order_closure.lock().unwrap().push(tag);
+            })
+            .unwrap();
+        }
+
+        let order_closure = Arc::clone(&order);
+        bus.on(EventType::Start, move |_, _| {
+            order_closure.lock().unwrap().push("default");
+        })
+        .unwrap();
+
+        bus.emit(EventType::Start).expect("Failed to emit");
+
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec!["validation", "audit", "business", "business 2", "default"]
+        );
+    }
+
+    #[test]
+    fn on_any() {
+        let bus: EventBus<u8, u8> = EventBus::unbound();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let log_closure = Arc::clone(&log);
+        bus.on(1, move |_, value| {
+            log_closure
+                .lock()
+                .unwrap()
+                .push((1, value.copied(), "specific"));
+        })
+        .unwrap();
+
+        let log_closure = Arc::clone(&log);
+        let subscription = bus
+            .on_any(move |_, event, value| {
+                log_closure
+                    .lock()
+                    .unwrap()
+                    .push((*event, value.copied(), "any"));
+            })
+            .unwrap();
+
+        let log_closure = Arc::clone(&log);
+        bus.on(1, move |_, value| {
+            log_closure
+                .lock()
+                .unwrap()
+                .push((1, value.copied(), "specific 2"));
+        })
+        .unwrap();
+
+        bus.emit_with_value(1, Some(&10)).expect("Failed to emit");
+        bus.emit(2).expect("Failed to emit");
+        assert!(bus.off(subscription).unwrap());
+        bus.emit(3).expect("Failed to emit");
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                (1, Some(10), "specific"),
+                (1, Some(10), "any"),
+                (1, Some(10), "specific 2"),
+                (2, None, "any"),
+            ]
+        );
+    }
+
+    #[test]
+    fn on_filtered() {
+        let bus: EventBus<u8, u8> = EventBus::unbound();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let log_closure = Arc::clone(&log);
+        bus.on_filtered(
+            1,
+            |_, value| value.is_some_and(|v| *v > 10),
+            move |_, value| {
+                log_closure.lock().unwrap().push((1, value.copied()));
+            },
+        )
+        .unwrap();
+
+        let log_closure = Arc::clone(&log);
+        bus.on_any_filtered(
+            |event, _| *event != 1,
+            move |_, event, value| {
+                log_closure.lock().unwrap().push((*event, value.copied()));
+            },
+        )
+        .unwrap();
+
+        bus.emit_with_value(1, Some(&5)).expect("Failed to emit");
+        bus.emit_with_value(1, Some(&50)).expect("Failed to emit");
+        bus.emit(1).expect("Failed to emit");
+        bus.emit_with_value(2, Some(&5)).expect("Failed to emit");
+
+        assert_eq!(*log.lock().unwrap(), vec![(1, Some(50)), (2, Some(5))]);
+    }
+
+    #[test]
+    fn emit_mut() {
+        let bus: EventBus<EventType, String> = EventBus::unbound();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        bus.on_mut(EventType::Start, |_, value| value.push_str(" world"))
+            .unwrap();
+
+        let log_closure = Arc::clone(&log);
+        bus.on(EventType::Start, move |_, value| {
+            log_closure.lock().unwrap().push(value.cloned());
+        })
+        .unwrap();
+
+        bus.on_mut(EventType::Start, |_, value| {
+            *value = value.to_uppercase();
+        })
+        .unwrap();
+
+        let mut value = "hello".to_owned();
+        bus.emit_mut(EventType::Start, &mut value)
+            .expect("Failed to emit");
+        bus.emit_with_value(EventType::Start, Some(&"untouched".to_owned()))
+            .expect("Failed to emit");
+
+        assert_eq!(value, "HELLO WORLD");
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![Some("hello world".to_owned()), Some("untouched".to_owned())]
+        );
+        assert_eq!(bus.event_count(), 2);
+    }
+
+    #[test]
+    fn emit_collect() {
+        let bus: EventBus<EventType, u8> = EventBus::unbound();
+
+        bus.on_collect(EventType::Stop, |_, pending| pending.is_none())
+            .unwrap();
+        bus.on_with_priority(EventType::Stop, 1, |_, _| {}).unwrap();
+        bus.on_collect(EventType::Stop, |_, pending| {
+            pending.is_some_and(|p| *p < 5)
+        })
+        .unwrap();
+
+        let votes: Vec<bool> = bus
+            .emit_collect(EventType::Stop, Some(&3))
+            .expect("Failed to emit");
+        assert_eq!(votes, vec![false, true]);
+
+        let votes: Vec<bool> = bus
+            .emit_collect(EventType::Start, None)
+            .expect("Failed to emit");
+        assert!(votes.is_empty());
+
+        let id = bus
+            .on_collect(EventType::Stop, |_, _| "not a vote")
+            .unwrap();
+        assert_eq!(
+            bus.emit_collect::<bool>(EventType::Stop, None),
+            Err(Error::ReturnTypeMismatch { subscription: id })
+        );
+
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert_eq!(bus.event_count(), 4);
+    }
+
+    #[test]
+    fn stop_propagation() {
+        let bus: EventBus<u8, bool> = EventBus::unbound();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let log_closure = Arc::clone(&log);
+        bus.on_with_priority(1, 10, move |inner_bus, handled| {
+            log_closure.lock().unwrap().push("first");
+            if handled == Some(&true) {
+                inner_bus.stop_propagation();
+            }
+        })
+        .unwrap();
+
+        let log_closure = Arc::clone(&log);
+        bus.on(1, move |_, _| {
+            log_closure.lock().unwrap().push("second");
+        })
+        .unwrap();
+
+        assert_eq!(
+            bus.emit_controlled(1, Some(&true)),
+            Ok(ControlFlow::Break(()))
+        );
+        assert_eq!(
+            bus.emit_controlled(1, Some(&false)),
+            Ok(ControlFlow::Continue(()))
+        );
+
+        assert_eq!(*log.lock().unwrap(), vec!["first", "first", "second"]);
+    }
+
+    #[test]
+    fn stop_propagation_after_panic() {
+        let bus: EventBus<u8, ()> = EventBus::unbound();
+        let other: EventBus<u8, ()> = EventBus::unbound();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        other.on(1, |_, _| panic!("Failed to handle 1")).unwrap();
+
+        bus.on_with_priority(1, 10, move |inner_bus, _| {
+            let panicked = panic::catch_unwind(AssertUnwindSafe(|| other.emit(1)));
+            assert!(panicked.is_err());
+            inner_bus.stop_propagation();
+        })
+        .unwrap();
+
+        let log_closure = Arc::clone(&log);
+        bus.on(1, move |_, _| {
+            log_closure.lock().unwrap().push("second");
+        })
+        .unwrap();
+
+        bus.emit(1).expect("Failed to emit");
+        assert!(log.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn stop_propagation_re_emit() {
+        let bus: EventBus<u8, ()> = EventBus::unbound();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        bus.on(1, |inner_bus, _| {
+            inner_bus.emit(2).expect("Cannot emit 2");
+        })
+        .unwrap();
+
+        bus.on(2, |inner_bus, _| inner_bus.stop_propagation())
+            .unwrap();
+
+        let log_closure = Arc::clone(&log);
+        bus.on_any(move |_, event, _| {
+            log_closure.lock().unwrap().push(*event);
+        })
+        .unwrap();
+
+        assert_eq!(bus.emit_controlled(1, None), Ok(ControlFlow::Continue(())));
+        assert_eq!(*log.lock().unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn try_emit() {
+        let bus: EventBus<EventType, &str> = EventBus::unbound();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let first = bus
+            .try_on(EventType::Start, |_, value| {
+                value.unwrap().parse::<u8>()?;
+                Ok(())
+            })
+            .unwrap();
+
+        let log_closure = Arc::clone(&log);
+        bus.on(EventType::Start, move |_, value| {
+            log_closure.lock().unwrap().push(value.copied());
+        })
+        .unwrap();
+
+        let second = bus
+            .try_on(EventType::Start, |_, _| Err("always fails".into()))
+            .unwrap();
+
+        assert!(bus.try_emit(EventType::Stop, Some(&"x")).is_ok());
+
+        match bus.try_emit(EventType::Start, Some(&"x")) {
+            Err(EmitError::Listeners { event, failures }) => {
+                assert!(event == EventType::Start);
+                let ids = failures.iter().map(|f| f.subscription).collect::<Vec<_>>();
+                assert_eq!(ids, vec![first, second]);
+                assert_eq!(failures[1].error.to_string(), "always fails");
+            }
+            _ => panic!("Listener failures were not reported"),
         }
-    }
 
-    /// Creates a bound bus that can emit up to `limit` events
-    pub fn bound(limit: usize) -> Self {
-        Self {
-            bus: Arc::new(BusRef::bound(limit)),
+        bus.set_stop_on_error(true);
+        match bus.try_emit(EventType::Start, Some(&"y")) {
+            Err(EmitError::Listeners { failures, .. }) => assert_eq!(failures.len(), 1),
+            _ => panic!("Listener failures were not reported"),
         }
-    }
 
-    /// Returns `true` if this bus has exausted its allowed max number of emits
-    pub fn disconnected(&self) -> bool {
-        let bus_lock = self.bus_ref();
-        bus_lock.disconnected()
-    }
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let errors_closure = Arc::clone(&errors);
+        bus.on_error(move |error| {
+            if let Error::ListenerFailed { subscription, .. } = error {
+                errors_closure.lock().unwrap().push(*subscription);
+            }
+        })
+        .unwrap();
 
-    fn bus_ref(&self) -> &BusRef<E, V> {
-        self.bus.as_ref()
-    }
+        bus.emit_with_value(EventType::Start, Some(&"z"))
+            .expect("Failed to emit");
+        assert_eq!(*errors.lock().unwrap(), vec![first, second]);
 
-    pub fn event_count(&self) -> usize {
-        self.bus_ref().event_count()
+        assert_eq!(*log.lock().unwrap(), vec![Some("x"), Some("z")]);
     }
-}
 
-impl<E, V> EventEmitter<E, V> for EventBus<E, V>
-where
-    E: Eq + Hash,
-{
-    fn on<F>(&self, event: E, f: F) -> Result<(), Error>
-    where
-        F: Fn(&BusRef<E, V>, Option<&V>) + 'static,
-    {
-        self.bus_ref().on(event, f)
+    #[test]
+    fn catch_panics() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let log_closure = Arc::clone(&log);
+        bus.on_error(move |error| {
+            log_closure.lock().unwrap().push(format!("{:?}", error));
+        })
+        .unwrap();
+        bus.set_catch_panics(true);
+
+        let faulty = bus
+            .on(EventType::Start, |_, _| panic!("broken plugin"))
+            .unwrap();
+
+        let log_closure = Arc::clone(&log);
+        bus.on(EventType::Start, move |_, _| {
+            log_closure
+                .lock()
+                .unwrap()
+                .push("second listener".to_owned());
+        })
+        .unwrap();
+
+        bus.emit(EventType::Start).expect("Failed to emit");
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                format!(
+                    "{:?}",
+                    Error::ListenerPanicked {
+                        subscription: faulty,
+                        message: "broken plugin".to_owned()
+                    }
+                ),
+                "second listener".to_owned()
+            ]
+        );
+        assert_eq!(bus.event_count(), 1);
     }
 
-    fn emit(&self, event: E) -> Result<(), Error> {
-        self.emit_with_value(event, None)
+    #[test]
+    fn limit_errors() {
+        fn emit_twice(bus: &EventBus<EventType, ()>) -> Result<(), Box<dyn std::error::Error>> {
+            bus.emit(EventType::Start)?;
+            bus.emit(EventType::Stop)?;
+            Ok(())
+        }
+
+        let bus: EventBus<EventType, ()> = EventBus::bound(3);
+        assert!(emit_twice(&bus).is_ok());
+
+        let error = emit_twice(&bus).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<Error>(),
+            Some(&Error::Disconnected { limit: 3, count: 3 })
+        );
+        assert_eq!(
+            error.to_string(),
+            "the bus has reached its limit of 3 emits (3 emitted)"
+        );
     }
 
-    fn emit_with_value(&self, event: E, value: Option<&V>) -> Result<(), Error> {
-        self.bus_ref().emit_with_value(event, value)
+    #[test]
+    fn dead_letters() {
+        let bus: EventBus<&str, u8> = EventBus::unbound();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let log_closure = Arc::clone(&log);
+        bus.on_unhandled(move |_, event, value| {
+            log_closure
+                .lock()
+                .unwrap()
+                .push((event.to_string(), value.copied()));
+        })
+        .unwrap();
+
+        bus.on("start", |_, _| {}).unwrap();
+        let once = bus.once("stop", |_, _| {}).unwrap();
+
+        bus.emit_with_value("start", Some(&1))
+            .expect("Failed to emit");
+        bus.emit_with_value("strat", Some(&2))
+            .expect("Failed to emit");
+        bus.emit("stop").expect("Failed to emit");
+        bus.emit("stop").expect("Failed to emit");
+        assert!(!bus.off(once).unwrap());
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![("strat".to_owned(), Some(2)), ("stop".to_owned(), None)]
+        );
+        assert_eq!(bus.unhandled_count(), 2);
+        assert_eq!(bus.event_count(), 4);
     }
-}
 
-impl<E, V> Clone for EventBus<E, V> {
-    fn clone(&self) -> Self {
-        Self {
-            bus: Arc::clone(&self.bus),
-        }
+    #[test]
+    fn dead_letters_of_skipped_emits() {
+        let bus: EventBus<&str, u8> = EventBus::unbound();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let log_closure = Arc::clone(&log);
+        bus.on_unhandled(move |_, event, value| {
+            log_closure
+                .lock()
+                .unwrap()
+                .push((event.to_string(), value.copied()));
+        })
+        .unwrap();
+
+        bus.on_filtered("start", |_, value| value == Some(&1), |_, _| {})
+            .unwrap();
+        bus.on_mut("stop", |_, value| *value += 1).unwrap();
+
+        bus.emit_with_value("start", Some(&1))
+            .expect("Failed to emit");
+        bus.emit_with_value("start", Some(&2))
+            .expect("Failed to emit");
+        bus.emit_mut("stop", &mut 3).expect("Failed to emit");
+        bus.emit_with_value("stop", Some(&4))
+            .expect("Failed to emit");
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![("start".to_owned(), Some(2)), ("stop".to_owned(), Some(4))]
+        );
+        assert_eq!(bus.unhandled_count(), 2);
     }
-}
 
-unsafe impl<E, V> Send for EventBus<E, V> where E: Send {}
+    #[test]
+    fn event_limits() {
+        let limit = PerEvent::new().limit("init", 1).limit("retry", 2);
+        let bus: EventBus<&str, ()> = EventBus::with_limit(limit);
+        let log = Arc::new(Mutex::new(Vec::new()));
 
-unsafe impl<E, V> Sync for EventBus<E, V> where E: Send {}
+        for event in ["init", "retry", "tick"] {
+            let log_closure = Arc::clone(&log);
+            bus.on(event, move |_, _| {
+                log_closure.lock().unwrap().push(event);
+            })
+            .unwrap();
+        }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+        bus.emit("init").expect("Failed to emit");
+        assert_eq!(
+            bus.emit("init"),
+            Err(Error::EventDisconnected { limit: 1, count: 1 })
+        );
+        assert!(bus.event_disconnected(&"init"));
 
-    use std::{cell::RefCell, rc::Rc, sync::Mutex};
-        
-    #[derive(PartialEq, Eq, Hash)]
-    enum EventType {
-        Start,
-        Stop,
+        bus.emit("retry").expect("Failed to emit");
+        bus.emit("tick").expect("Failed to emit");
+        bus.emit("retry").expect("Failed to emit");
+        assert!(bus.emit("retry").is_err());
+        bus.emit("tick").expect("Failed to emit");
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["init", "retry", "tick", "retry", "tick"]
+        );
+        assert!(!bus.event_disconnected(&"tick"));
+        assert!(!bus.disconnected());
+        assert_eq!(bus.event_count(), 5);
     }
 
-    #[derive(Debug, PartialEq)]
-    enum Status {
-        Stopped,
-        Started,
+    #[test]
+    fn limit_policies() {
+        let bus: EventBus<EventType, ()> = EventBus::bound(0);
+        assert!(bus.disconnected());
+        assert_eq!(
+            bus.emit(EventType::Start),
+            Err(Error::Disconnected { limit: 0, count: 0 })
+        );
+
+        let bus: EventBus<EventType, ()> =
+            EventBus::with_limit(PerEvent::new().limit(EventType::Start, 1));
+        bus.emit(EventType::Start).expect("Failed to emit");
+        assert!(bus.emit(EventType::Start).is_err());
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert!(!bus.disconnected());
+
+        let clock = ManualClock::new();
+        let window = Duration::from_secs(1);
+        let limit = SlidingWindow::with_clock(2, window, clock.clone());
+        let bus: EventBus<EventType, ()> = EventBus::with_limit(limit);
+        bus.emit(EventType::Start).expect("Failed to emit");
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert_eq!(
+            bus.emit(EventType::Start),
+            Err(Error::Throttled { limit: 2, window })
+        );
+        clock.advance(window / 2);
+        assert!(bus.emit(EventType::Start).is_err());
+        clock.advance(window / 2);
+        bus.emit(EventType::Start).expect("Failed to emit");
+        assert_eq!(bus.event_count(), 3);
     }
 
     #[test]
-    fn create_bus() {
-        let _bus: EventBus<u8, ()> = EventBus::unbound();
+    fn limit_released_on_failure() {
+        let bus: EventBus<EventType, ()> = EventBus::bound(1);
+
+        let bus_clone = bus.clone();
+        let poisoner = std::thread::spawn(move || {
+            let _listeners = bus_clone.bus.listeners.write().unwrap();
+            panic!("Poisoning the listeners");
+        });
+        assert!(poisoner.join().is_err());
+
+        assert_eq!(bus.emit(EventType::Start), Err(Error::Poisoned));
+        assert!(!bus.disconnected());
+        assert_eq!(bus.event_count(), 0);
     }
 
     #[test]
-    fn listen_emit_api() {
-        let bus: EventBus<EventType, ()> = EventBus::unbound();
-        let status = Rc::new(RefCell::new(Status::Stopped));
-        let status_closure = Rc::clone(&status);
-        let status_closure_2 = Rc::clone(&status);
-        bus.on(EventType::Start, move |_, _| {
-            *status_closure.borrow_mut() = Status::Started;
-        })
-        .unwrap();
+    fn combined_limit_policies() {
+        let limit = (Total::new(3), PerEvent::new().limit(EventType::Stop, 1));
+        let bus: EventBus<EventType, ()> = EventBus::with_limit(limit);
+        let log = Arc::new(Mutex::new(Vec::new()));
 
+        let log_closure = Arc::clone(&log);
         bus.on(EventType::Stop, move |_, _| {
-            *status_closure_2.borrow_mut() = Status::Stopped;
+            log_closure.lock().unwrap().push("stopped");
         })
         .unwrap();
 
-        bus.emit(EventType::Start)
-            .expect("Failed to emit START event");
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert_eq!(
+            bus.emit(EventType::Stop),
+            Err(Error::EventDisconnected { limit: 1, count: 1 })
+        );
+        assert!(bus.event_disconnected(&EventType::Stop));
+        assert!(!bus.disconnected());
+
+        // The refused emit of `Stop` doesn't count against the total
+        bus.emit(EventType::Start).expect("Failed to emit");
+        bus.emit(EventType::Start).expect("Failed to emit");
+        assert_eq!(
+            bus.emit(EventType::Start),
+            Err(Error::Disconnected { limit: 3, count: 3 })
+        );
+        assert!(bus.disconnected());
 
-        assert_eq!(*status.borrow(), Status::Started);
+        bus.reset().unwrap();
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert_eq!(*log.lock().unwrap(), vec!["stopped", "stopped"]);
         assert_eq!(bus.event_count(), 1);
+    }
 
-        bus.emit(EventType::Stop)
-            .expect("Failed to emit STOP event");
+    #[test]
+    fn custom_limit_policy() {
+        /// Only lets `Stop` through once `Start` has been emitted
+        struct StartFirst(AtomicBool);
+
+        impl EmitLimit<EventType> for StartFirst {
+            fn acquire(&self, event: &EventType) -> Result<Admission, Error> {
+                match event {
+                    EventType::Start => {
+                        self.0.store(true, Ordering::SeqCst);
+                        Ok(Admission::Emit)
+                    }
+                    EventType::Stop if self.0.load(Ordering::SeqCst) => Ok(Admission::Emit),
+                    EventType::Stop => Err(Error::Rejected {
+                        reason: "Stop before Start".to_owned(),
+                    }),
+                }
+            }
+        }
 
-        assert_eq!(*status.borrow(), Status::Stopped);
+        let bus: EventBus<EventType, ()> = EventBus::with_limit(StartFirst(AtomicBool::new(false)));
+        let error = bus.emit(EventType::Stop).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the emit was rejected: Stop before Start"
+        );
+        bus.emit(EventType::Start).expect("Failed to emit");
+        bus.emit(EventType::Stop).expect("Failed to emit");
         assert_eq!(bus.event_count(), 2);
     }
 
     #[test]
-    fn listen_emit_api_repeat() {
-        let bus: EventBus<u8, ()> = EventBus::unbound();
-        let status = Rc::new(RefCell::new(0));
-        let status2 = Rc::clone(&status);
-        bus.on(1u8, move |_, _| {
-            *status2.borrow_mut() += 1;
+    fn rate_limits() {
+        let clock = ManualClock::new();
+        let second = Duration::from_secs(1);
+        let limit = RateLimit::with_clock(clock.clone()).limit(EventType::Start, 2, second);
+        let bus: EventBus<EventType, ()> = EventBus::with_limit(limit);
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let log_closure = Arc::clone(&log);
+        bus.on_unhandled(move |_, _, _| {
+            log_closure.lock().unwrap().push("unhandled");
         })
         .unwrap();
 
-        for _ in 0..4 {
-            bus.emit(1).expect("Failed to emit");
+        bus.emit(EventType::Start).expect("Failed to emit");
+        bus.emit(EventType::Start).expect("Failed to emit");
+        assert_eq!(
+            bus.emit(EventType::Start),
+            Err(Error::RateLimited {
+                limit: 2,
+                per: second
+            })
+        );
+        bus.emit(EventType::Stop).expect("Failed to emit");
+
+        clock.advance(second / 2);
+        bus.emit(EventType::Start).expect("Failed to emit");
+        assert!(bus.emit(EventType::Start).is_err());
+        assert_eq!(bus.event_count(), 4);
+        assert_eq!(log.lock().unwrap().len(), 4);
+
+        let limit = RateLimit::with_clock(clock.clone())
+            .limit(EventType::Start, 2, second)
+            .overflow(Overflow::Drop);
+        let bus: EventBus<EventType, ()> = EventBus::with_limit(limit);
+        for _ in 0..3 {
+            bus.emit(EventType::Start).expect("Failed to emit");
         }
+        assert_eq!(bus.event_count(), 2);
+        assert_eq!(bus.unhandled_count(), 2);
 
-        assert_eq!(*status.borrow(), 4);
+        let started = clock.now();
+        let limit = RateLimit::with_clock(clock.clone())
+            .limit(EventType::Start, 2, second)
+            .overflow(Overflow::Defer);
+        let bus: EventBus<EventType, ()> = EventBus::with_limit(limit);
+        for _ in 0..4 {
+            bus.emit(EventType::Start).expect("Failed to emit");
+        }
         assert_eq!(bus.event_count(), 4);
+        assert_eq!(clock.now() - started, second);
+
+        let limit = RateLimit::with_clock(clock.clone())
+            .limit(EventType::Start, 0, second)
+            .limit(EventType::Stop, 1, Duration::ZERO)
+            .overflow(Overflow::Defer);
+        let bus: EventBus<EventType, ()> = EventBus::with_limit(limit);
+        assert_eq!(
+            bus.emit(EventType::Start),
+            Err(Error::RateLimited {
+                limit: 0,
+                per: second
+            })
+        );
+        for _ in 0..3 {
+            bus.emit(EventType::Stop).expect("Failed to emit");
+        }
+        assert_eq!(bus.event_count(), 3);
     }
 
     #[test]
-    fn threaded_on() {
+    fn close() {
         let bus: EventBus<EventType, ()> = EventBus::unbound();
-        let bus_clone = bus.clone();
-        let status = Arc::new(Mutex::new(Status::Stopped));
-        let final_status = Arc::clone(&status);
+        let log = Arc::new(Mutex::new(Vec::new()));
 
-        let t1 = std::thread::spawn(move || {
-            bus_clone
-                .on(EventType::Start, move |_, _| {
-                    let mut status_lock = status.lock().unwrap();
-                    *status_lock = Status::Started;
-                })
-                .unwrap();
-        });
+        let log_closure = Arc::clone(&log);
+        bus.on(EventType::Start, move |_, _| {
+            log_closure.lock().unwrap().push("started");
+        })
+        .unwrap();
 
-        t1.join().unwrap();
+        for name in ["cache", "socket"] {
+            let log_closure = Arc::clone(&log);
+            bus.on_close(move |bus| {
+                assert_eq!(bus.emit(EventType::Stop), Err(Error::Closed));
+                log_closure.lock().unwrap().push(name);
+            })
+            .unwrap();
+        }
 
-        bus.emit(EventType::Start).unwrap();
+        bus.emit(EventType::Start).expect("Failed to emit");
+        assert!(!bus.is_closed());
+        bus.close();
+        bus.close();
 
-        let final_status_lock = final_status.lock().unwrap();
-        assert_eq!(*final_status_lock, Status::Started)
+        assert!(bus.is_closed());
+        assert_eq!(bus.emit(EventType::Start), Err(Error::Closed));
+        assert_eq!(bus.on(EventType::Stop, |_, _| {}), Err(Error::Closed));
+        assert_eq!(bus.on_close(|_| {}), Err(Error::Closed));
+        assert_eq!(bus.reset(), Err(Error::Closed));
+        assert_eq!(*log.lock().unwrap(), vec!["started", "cache", "socket"]);
+        assert_eq!(bus.event_count(), 1);
+
+        // The listeners and hooks holding the log have been dropped
+        assert_eq!(bus.listener_count(&EventType::Start), 0);
+        assert_eq!(Arc::strong_count(&log), 1);
+
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        bus.on(EventType::Stop, |bus, _| bus.close()).unwrap();
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert_eq!(bus.listener_count(&EventType::Stop), 0);
     }
 
     #[test]
-    fn threaded_emit() {
-        let bus: EventBus<EventType, ()> = EventBus::unbound();
-        let bus_clone = bus.clone();
-        let status = Arc::new(Mutex::new(Status::Stopped));
-        let final_status = Arc::clone(&status);
+    fn reset() {
+        let bus: EventBus<EventType, ()> = EventBus::bound(2);
+        bus.emit(EventType::Start).expect("Failed to emit");
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert!(bus.disconnected());
 
-        let t1 = std::thread::spawn(move || {
-            bus.emit(EventType::Start).unwrap();
-        });
+        bus.reset().unwrap();
+        assert!(!bus.disconnected());
+        assert_eq!(bus.event_count(), 0);
 
-        bus_clone
-            .on(EventType::Start, move |_, _| {
-                let mut status_lock = status.lock().unwrap();
-                *status_lock = Status::Started;
-            })
-            .unwrap();
+        let bus: EventBus<EventType, ()> =
+            EventBus::with_limit(PerEvent::new().limit(EventType::Stop, 1));
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert!(bus.event_disconnected(&EventType::Stop));
 
-        t1.join().unwrap();
+        bus.reset().unwrap();
+        assert!(!bus.event_disconnected(&EventType::Stop));
+        assert_eq!(bus.event_count(), 0);
 
-        let final_status_lock = final_status.lock().unwrap();
-        assert_eq!(*final_status_lock, Status::Started)
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert!(bus.emit(EventType::Stop).is_err());
+        assert_eq!(bus.event_count(), 1);
     }
 
     #[test]
-    fn with_data() {
-        let bus: EventBus<EventType, u8> = EventBus::unbound();
-        let status: Rc<RefCell<Option<u8>>> = Rc::new(RefCell::new(None));
-        let status_closure = Rc::clone(&status);
+    fn max_listeners() {
+        let bus: EventBus<&str, ()> = EventBus::unbound();
+        let warnings = Arc::new(Mutex::new(Vec::new()));
 
-        bus.on(EventType::Start, move |_, startup_data: Option<&u8>| {
-            *status_closure.borrow_mut() = Some(*startup_data.unwrap());
+        let warnings_closure = Arc::clone(&warnings);
+        bus.on_max_listeners(move |_, event, count| {
+            warnings_closure
+                .lock()
+                .unwrap()
+                .push(format!("{} has {} listeners", event, count));
         })
         .unwrap();
+        bus.set_max_listeners("render", 2).unwrap();
 
-        bus.emit_with_value(EventType::Start, Some(&123))
+        for _ in 0..4 {
+            bus.on("render", |_, _| {}).unwrap();
+        }
+        bus.on_any(|_, _, _| {}).unwrap();
+        assert_eq!(bus.listener_count(&"render"), 4);
+        assert_eq!(*warnings.lock().unwrap(), vec!["render has 3 listeners"]);
+
+        bus.set_strict_max_listeners(true);
+        assert_eq!(
+            bus.on("render", |_, _| {}),
+            Err(Error::TooManyListeners { limit: 2, count: 5 })
+        );
+        bus.on("resize", |_, _| {}).unwrap();
+        assert_eq!(bus.listener_count(&"render"), 4);
+    }
+
+    #[test]
+    fn on_variant() {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        enum UserEvent {
+            LoggedIn { id: u32 },
+            LoggedOut { id: u32 },
+        }
+
+        let bus: EventBus<UserEvent, ()> = EventBus::unbound();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let log_closure = Arc::clone(&log);
+        bus.on(UserEvent::LoggedIn { id: 2 }, move |_, _| {
+            log_closure
+                .lock()
+                .unwrap()
+                .push((UserEvent::LoggedIn { id: 2 }, "specific"));
+        })
+        .unwrap();
+
+        let log_closure = Arc::clone(&log);
+        let subscription = bus
+            .on_variant(UserEvent::LoggedIn { id: 0 }, move |_, event, _| {
+                log_closure.lock().unwrap().push((*event, "variant"));
+            })
+            .unwrap();
+
+        bus.emit(UserEvent::LoggedIn { id: 1 })
+            .expect("Failed to emit");
+        bus.emit(UserEvent::LoggedIn { id: 2 })
+            .expect("Failed to emit");
+        bus.emit(UserEvent::LoggedOut { id: 1 })
+            .expect("Failed to emit");
+        assert!(bus.off(subscription).unwrap());
+        bus.emit(UserEvent::LoggedIn { id: 3 })
             .expect("Failed to emit");
 
-        assert_eq!(*status.borrow(), Some(123));
-        assert_eq!(bus.event_count(), 1);
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                (UserEvent::LoggedIn { id: 1 }, "variant"),
+                (UserEvent::LoggedIn { id: 2 }, "specific"),
+                (UserEvent::LoggedIn { id: 2 }, "variant"),
+            ]
+        );
     }
 
     #[test]
-    #[should_panic]
-    fn exceed_bounds() {
-        let bus: EventBus<EventType, u8> = EventBus::bound(5);
-        for i in 0..10 {
-            bus.emit_with_value(EventType::Start, Some(&i))
-                .expect("Failed to emit");
+    fn topics() {
+        let bus: TopicBus<u8> = TopicBus::new();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let mut subscriptions = Vec::new();
+        for pattern in ["order.*", "order.#", "#.shipped", "invoice.#"] {
+            let log_closure = Arc::clone(&log);
+            let subscription = bus
+                .on(pattern, move |_, topic, value| {
+                    log_closure
+                        .lock()
+                        .unwrap()
+                        .push((pattern, topic.to_owned(), value.copied()));
+                })
+                .unwrap();
+            subscriptions.push(subscription);
         }
+
+        bus.emit_with_value("order.shipped", Some(&1))
+            .expect("Failed to emit");
+        assert!(bus.off(subscriptions[1]).unwrap());
+        bus.emit("order.eu.shipped").expect("Failed to emit");
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                ("order.*", "order.shipped".to_owned(), Some(1)),
+                ("order.#", "order.shipped".to_owned(), Some(1)),
+                ("#.shipped", "order.shipped".to_owned(), Some(1)),
+                ("#.shipped", "order.eu.shipped".to_owned(), None),
+            ]
+        );
+        assert_eq!(bus.event_count(), 2);
     }
 
     #[test]
-    fn re_emit() {
-        let bus: EventBus<EventType, u8> = EventBus::unbound();
-        // let bus_2: EventBus<EventType, u8> = bus.clone();
-        let status: Rc<RefCell<Option<u8>>> = Rc::new(RefCell::new(None));
-        let status_closure = Rc::clone(&status);
-        let status_closure_2 = Rc::clone(&status);
+    fn topics_re_emit() {
+        let bus: TopicBus<()> = TopicBus::new();
+        let log = Arc::new(Mutex::new(Vec::new()));
 
-        bus.on(EventType::Start, move |inner_bus, startup_data| {
-            *status_closure.borrow_mut() = Some(*startup_data.unwrap());
+        bus.on("order.created", |inner_bus, _, _| {
             inner_bus
-                .emit(EventType::Stop)
-                .expect("Cannot emit STOP event");
+                .emit("order.validated")
+                .expect("Cannot emit order.validated");
         })
         .unwrap();
 
-        bus.on(EventType::Stop, move |_, _| {
-            *status_closure_2.borrow_mut() = None;
+        let log_closure = Arc::clone(&log);
+        bus.on("order.#", move |_, topic, _| {
+            log_closure.lock().unwrap().push(topic.to_owned());
         })
         .unwrap();
 
-        bus.emit_with_value(EventType::Start, Some(&123))
-            .expect("Failed to emit");
+        bus.emit("order.created").expect("Failed to emit");
 
-        assert_eq!(*status.borrow(), None);
-        assert_eq!(bus.event_count(), 2);
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["order.validated", "order.created"]
+        );
+    }
+
+    #[test]
+    fn invalid_topics() {
+        let bus: TopicBus<()> = TopicBus::new();
+
+        assert_eq!(
+            bus.on("order..created", |_, _, _| {}),
+            Err(Error::InvalidTopic)
+        );
+        assert_eq!(bus.emit("order.*"), Err(Error::InvalidTopic));
+        assert_eq!(bus.event_count(), 0);
+    }
+
+    #[test]
+    fn typed_events() {
+        struct Started(u8);
+        struct Stopped;
+
+        let bus = TypedBus::new();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let log_closure = Arc::clone(&log);
+        bus.on(move |_, event: &Started| {
+            log_closure
+                .lock()
+                .unwrap()
+                .push(format!("started {}", event.0));
+        })
+        .unwrap();
+
+        let log_closure = Arc::clone(&log);
+        let subscription = bus
+            .on(move |_, _: &Stopped| {
+                log_closure.lock().unwrap().push("stopped".to_owned());
+            })
+            .unwrap();
+
+        bus.emit(Started(1)).expect("Failed to emit");
+        bus.emit(Stopped).expect("Failed to emit");
+        bus.emit("unhandled").expect("Failed to emit");
+        assert!(bus.off(subscription).unwrap());
+        assert!(!bus.off(subscription).unwrap());
+        bus.emit(Stopped).expect("Failed to emit");
+
+        assert_eq!(*log.lock().unwrap(), vec!["started 1", "stopped"]);
+        assert_eq!(bus.event_count(), 4);
+    }
+
+    #[test]
+    fn typed_events_re_emit() {
+        struct Request(u8);
+        struct Response(u8);
+
+        let bus = TypedBus::new();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        bus.on(|inner_bus, request: &Request| {
+            inner_bus
+                .emit(Response(request.0 * 2))
+                .expect("Cannot emit a response");
+        })
+        .unwrap();
+
+        let log_closure = Arc::clone(&log);
+        bus.on(move |_, response: &Response| {
+            log_closure.lock().unwrap().push(response.0);
+        })
+        .unwrap();
+
+        bus.emit(Request(21)).expect("Failed to emit");
+
+        assert_eq!(*log.lock().unwrap(), vec![42]);
     }
 }
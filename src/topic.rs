@@ -0,0 +1,287 @@
+//! Hierarchical topics for `TopicBus`.
+//!
+//! A topic is a dot separated string such as `order.created` or
+//! `order.eu.shipped`. Listeners subscribe to patterns, which are topics
+//! whose segments may also be wildcards:
+//!
+//! - `*` matches exactly one segment, so `order.*` matches `order.created`
+//!   but neither `order` nor `order.eu.shipped`
+//! - `#` matches zero or more segments, so `order.#` matches `order`,
+//!   `order.created` and `order.eu.shipped`
+//!
+//! Subscriptions are kept in a trie indexed by segment, so emitting a topic
+//! only visits the branches that can match it instead of every pattern.
+//!
+//! See `unsync::TopicBus` and `sync::TopicBus`.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::prelude::Error;
+
+const SEPARATOR: char = '.';
+const SINGLE_WILDCARD: &str = "*";
+const MULTI_WILDCARD: &str = "#";
+
+/// A segment of a subscription pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Single,
+    Multi,
+}
+
+/// A validated subscription pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    segments: Vec<Segment>,
+}
+
+impl Pattern {
+    /// Parses `pattern`, failing with `Error::InvalidTopic` if it has empty
+    /// segments or wildcards mixed with other characters in a segment.
+    ///
+    /// Consecutive `#` are collapsed into one, as they match the same topics.
+    pub fn parse(pattern: &str) -> Result<Self, Error> {
+        let mut segments = pattern
+            .split(SEPARATOR)
+            .map(|segment| match segment {
+                SINGLE_WILDCARD => Ok(Segment::Single),
+                MULTI_WILDCARD => Ok(Segment::Multi),
+                literal => validate_literal(literal).map(|_| Segment::Literal(literal.to_owned())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        segments.dedup_by(|a, b| *a == Segment::Multi && *b == Segment::Multi);
+
+        Ok(Self { segments })
+    }
+
+    /// Whether this pattern matches `topic`
+    pub fn matches(&self, topic: &str) -> bool {
+        split_topic(topic).is_ok_and(|segments| matches_segments(&self.segments, &segments))
+    }
+}
+
+/// Whether the pattern made of `pattern` matches the topic made of `topic`
+fn matches_segments(pattern: &[Segment], topic: &[&str]) -> bool {
+    match (pattern.split_first(), topic.split_first()) {
+        (None, _) => topic.is_empty(),
+        (Some((Segment::Multi, rest)), _) => {
+            (0..=topic.len()).any(|skipped| matches_segments(rest, &topic[skipped..]))
+        }
+        (Some((Segment::Single, rest)), Some((_, tail))) => matches_segments(rest, tail),
+        (Some((Segment::Literal(literal), rest)), Some((head, tail))) => {
+            literal == head && matches_segments(rest, tail)
+        }
+        (Some(_), None) => false,
+    }
+}
+
+impl std::str::FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        Self::parse(pattern)
+    }
+}
+
+fn validate_literal(segment: &str) -> Result<(), Error> {
+    let wildcard = |c| SINGLE_WILDCARD.contains(c) || MULTI_WILDCARD.contains(c);
+    if segment.is_empty() || segment.contains(wildcard) {
+        Err(Error::InvalidTopic)
+    } else {
+        Ok(())
+    }
+}
+
+/// Splits an emitted topic into its segments, which can't be wildcards
+pub(crate) fn split_topic(topic: &str) -> Result<Vec<&str>, Error> {
+    topic
+        .split(SEPARATOR)
+        .map(|segment| validate_literal(segment).map(|_| segment))
+        .collect()
+}
+
+/// Values indexed by the pattern they were inserted with
+pub(crate) struct Trie<T> {
+    values: Vec<T>,
+    literals: HashMap<String, Trie<T>>,
+    single: Option<Box<Trie<T>>>,
+    multi: Option<Box<Trie<T>>>,
+}
+
+impl<T> Trie<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            values: Vec::new(),
+            literals: HashMap::new(),
+            single: None,
+            multi: None,
+        }
+    }
+
+    pub(crate) fn insert(&mut self, pattern: &Pattern, value: T) {
+        let node = pattern
+            .segments
+            .iter()
+            .fold(self, |node, segment| match segment {
+                Segment::Literal(literal) => node
+                    .literals
+                    .entry(literal.clone())
+                    .or_insert_with(Trie::new),
+                Segment::Single => node.single.get_or_insert_with(|| Box::new(Trie::new())),
+                Segment::Multi => node.multi.get_or_insert_with(|| Box::new(Trie::new())),
+            });
+        node.values.push(value);
+    }
+
+    /// Removes the values matching `predicate`, returning whether there
+    /// was any
+    pub(crate) fn remove_where<P>(&mut self, predicate: &P) -> bool
+    where
+        P: Fn(&T) -> bool,
+    {
+        let before = self.values.len();
+        self.values.retain(|value| !predicate(value));
+        let mut removed = self.values.len() != before;
+
+        self.literals.retain(|_, node| {
+            removed |= node.remove_where(predicate);
+            !node.is_empty()
+        });
+
+        for wildcard in [&mut self.single, &mut self.multi] {
+            if let Some(node) = wildcard {
+                removed |= node.remove_where(predicate);
+                if node.is_empty() {
+                    *wildcard = None;
+                }
+            }
+        }
+
+        removed
+    }
+
+    fn is_empty(&self) -> bool {
+        self.values.is_empty()
+            && self.literals.is_empty()
+            && self.single.is_none()
+            && self.multi.is_none()
+    }
+
+    /// The values whose pattern matches the topic made of `segments`, each
+    /// returned once even if its pattern matches the topic in several ways
+    /// (e.g. `#.order.#` for `order.order`).
+    pub(crate) fn matches(&self, segments: &[&str]) -> Vec<&T> {
+        let mut matches = Vec::new();
+        self.collect(segments, &mut HashSet::new(), &mut matches);
+        matches
+    }
+
+    /// Walks the branches matching `segments`, skipping the nodes already
+    /// reached with as many segments left through another branch
+    fn collect<'a>(
+        &'a self,
+        segments: &[&str],
+        visited: &mut HashSet<(*const Self, usize)>,
+        matches: &mut Vec<&'a T>,
+    ) {
+        if !visited.insert((self, segments.len())) {
+            return;
+        }
+
+        match segments.split_first() {
+            Some((head, rest)) => {
+                if let Some(node) = self.literals.get(*head) {
+                    node.collect(rest, visited, matches);
+                }
+                if let Some(node) = &self.single {
+                    node.collect(rest, visited, matches);
+                }
+            }
+            None => matches.extend(&self.values),
+        }
+
+        if let Some(node) = &self.multi {
+            for skipped in 0..=segments.len() {
+                node.collect(&segments[skipped..], visited, matches);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn matches(pattern: &str, topic: &str) -> bool {
+        Pattern::parse(pattern).unwrap().matches(topic)
+    }
+
+    #[test]
+    fn literal_patterns() {
+        assert!(matches("order.created", "order.created"));
+        assert!(!matches("order.created", "order.shipped"));
+        assert!(!matches("order.created", "order.created.eu"));
+        assert!(!matches("order.created", "order"));
+    }
+
+    #[test]
+    fn single_wildcard() {
+        assert!(matches("order.*", "order.created"));
+        assert!(matches("*.created", "order.created"));
+        assert!(!matches("order.*", "order"));
+        assert!(!matches("order.*", "order.eu.shipped"));
+    }
+
+    #[test]
+    fn multi_wildcard() {
+        assert!(matches("order.#", "order"));
+        assert!(matches("order.#", "order.created"));
+        assert!(matches("order.#", "order.eu.shipped"));
+        assert!(matches("#.shipped", "order.eu.shipped"));
+        assert!(matches("order.#.shipped", "order.shipped"));
+        assert!(matches("#", "order.eu.shipped"));
+        assert!(matches("#.#.shipped", "shipped"));
+        assert!(!matches("order.#", "invoice.created"));
+        assert!(!matches("#.order.#.shipped", "order.eu"));
+    }
+
+    #[test]
+    fn multi_wildcards_collapse() {
+        assert_eq!(Pattern::parse("order.#.#.#"), Pattern::parse("order.#"));
+        assert_ne!(Pattern::parse("#.*.#"), Pattern::parse("#.*"));
+    }
+
+    #[test]
+    fn matches_once() {
+        let mut trie = Trie::new();
+        trie.insert(&Pattern::parse("#.order.#").unwrap(), 1);
+        trie.insert(&Pattern::parse("#.*").unwrap(), 2);
+
+        let mut matches = trie.matches(&["order", "order", "order"]);
+        matches.sort();
+        assert_eq!(matches, vec![&1, &2]);
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        assert_eq!(Pattern::parse("order..created"), Err(Error::InvalidTopic));
+        assert_eq!(Pattern::parse("order.cre*"), Err(Error::InvalidTopic));
+        assert_eq!(Pattern::parse(""), Err(Error::InvalidTopic));
+        assert_eq!(split_topic("order.*"), Err(Error::InvalidTopic));
+    }
+
+    #[test]
+    fn remove_prunes() {
+        let mut trie = Trie::new();
+        trie.insert(&Pattern::parse("order.*.shipped").unwrap(), 1);
+        trie.insert(&Pattern::parse("order.#").unwrap(), 2);
+
+        assert!(trie.remove_where(&|value| *value == 1));
+        assert!(!trie.remove_where(&|value| *value == 1));
+        assert!(trie.literals["order"].single.is_none());
+
+        assert!(trie.remove_where(&|value| *value == 2));
+        assert!(trie.is_empty());
+    }
+}
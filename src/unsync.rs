@@ -1,6 +1,20 @@
-use std::{hash::Hash, rc::Rc};
+use std::{
+    any::{Any, TypeId},
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    hash::Hash,
+    ops::ControlFlow,
+    rc::{Rc, Weak},
+};
 
-use crate::prelude::{BusRef, Error, EventEmitter};
+use crate::{
+    listener::Listener,
+    prelude::{
+        BoxError, BusRef, EmitError, EmitLimit, Error, Event, EventEmitter, Handler, IntoHandler,
+        SubscriptionId, Variant,
+    },
+    topic::{split_topic, Pattern, Trie},
+};
 
 /// An event bus that can be cloned. If you need to share the bus
 /// across threads use `sync::EventBus`.
@@ -47,11 +61,21 @@ impl<E, V> EventBus<E, V> {
         Self::construct(BusRef::unbound())
     }
 
-    /// Creates a bound bus that can emit up to `limit` events
+    /// Creates a bound bus that can emit up to `limit` events, none at all
+    /// if `limit` is 0
     pub fn bound(limit: usize) -> Self {
         Self::construct(BusRef::bound(limit))
     }
 
+    /// Creates a bus whose emits are bound by the `limit` policy, see the
+    /// `limit` module for the available ones.
+    pub fn with_limit<L>(limit: L) -> Self
+    where
+        L: EmitLimit<E> + 'static,
+    {
+        Self::construct(BusRef::with_limit(Box::new(limit)))
+    }
+
     fn construct(bus: BusRef<E, V>) -> Self {
         Self { bus: Rc::new(bus) }
     }
@@ -63,6 +87,89 @@ impl<E, V> EventBus<E, V> {
         self.bus.disconnected()
     }
 
+    /// Returns `true` if this bus has been closed with `close`
+    pub fn is_closed(&self) -> bool {
+        self.bus.is_closed()
+    }
+
+    /// Closes this bus, after which adding listeners or emitting events on
+    /// it fails with `Error::Closed`, then runs the hooks set with
+    /// `on_close`, in the order they were added, dropping its listeners
+    /// and hooks.
+    ///
+    /// Closing a bus that was already closed does nothing.
+    pub fn close(&self) {
+        self.bus.close()
+    }
+
+    /// Adds a hook that will call `f` once this bus gets closed.
+    pub fn on_close<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: Fn(&BusRef<E, V>) + 'static,
+    {
+        self.bus.on_close(f)
+    }
+
+    /// Resets the count of events emitted on this bus along with its limit
+    /// policy, so that a bus that reached its bounds can emit again.
+    pub fn reset(&self) -> Result<(), Error> {
+        self.bus.reset()
+    }
+
+    /// Sets whether `try_emit` stops at the first listener that fails,
+    /// rather than running every listener and reporting all failures.
+    pub fn set_stop_on_error(&self, stop: bool) {
+        self.bus.set_stop_on_error(stop)
+    }
+
+    /// Sets whether panics of the listeners of this bus are caught and
+    /// reported to the hook set with `on_error`, rather than unwinding
+    /// through the emit.
+    pub fn set_catch_panics(&self, catch: bool) {
+        self.bus.set_catch_panics(catch)
+    }
+
+    /// Sets whether adding a listener that brings an event over its max
+    /// number of listeners fails with `Error::TooManyListeners`, rather
+    /// than warning the hook set with `on_max_listeners`.
+    pub fn set_strict_max_listeners(&self, strict: bool) {
+        self.bus.set_strict_max_listeners(strict)
+    }
+
+    /// Sets the hook warned when an event gets more listeners than its max
+    /// number of listeners, along with their count, replacing the previous
+    /// one.
+    pub fn on_max_listeners<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: Fn(&BusRef<E, V>, &E, usize) + 'static,
+    {
+        self.bus.on_max_listeners(f)
+    }
+
+    /// The number of events emitted on this bus that no listener received
+    pub fn unhandled_count(&self) -> usize {
+        self.bus.unhandled_count()
+    }
+
+    /// Sets the handler receiving the events emitted while no listener
+    /// was there to receive them, along with their value, replacing the
+    /// previous one.
+    pub fn on_unhandled<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: Fn(&BusRef<E, V>, &E, Option<&V>) + 'static,
+    {
+        self.bus.on_unhandled(f)
+    }
+
+    /// Sets the hook receiving the errors this bus can't return to an
+    /// emitter, replacing the previous one.
+    pub fn on_error<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: Fn(&Error) + 'static,
+    {
+        self.bus.on_error(f)
+    }
+
     /// The current number of events emitted on this bus.
     ///
     /// This method can panic if the bus can't be locked for inquiry.
@@ -71,32 +178,196 @@ impl<E, V> EventBus<E, V> {
     }
 }
 
-impl<E, V> EventEmitter<E, V> for EventBus<E, V>
+impl<E, V> EventBus<E, V>
 where
     E: Eq + Hash,
 {
-    fn on<F>(&self, event: E, f: F) -> Result<(), Error>
+    /// Sets the max number of listeners of `event`, past which adding
+    /// another one is reported as a likely leak of subscriptions.
+    pub fn set_max_listeners(&self, event: E, max: usize) -> Result<(), Error> {
+        self.bus.set_max_listeners(event, max)
+    }
+
+    /// The number of listeners of `event`, not counting the listeners of
+    /// its variant nor of any event.
+    pub fn listener_count(&self, event: &E) -> usize {
+        self.bus.listener_count(event)
+    }
+
+    /// Returns `true` if the limit policy of this bus doesn't let it emit
+    /// `event` anymore, while other events may still be emitted.
+    pub fn event_disconnected(&self, event: &E) -> bool {
+        self.bus.event_disconnected(event)
+    }
+
+    /// Adds a listener for `event` like `on` does, returning a guard that
+    /// removes it when dropped.
+    pub fn subscribe<F>(&self, event: E, f: F) -> Result<Subscription<E, V>, Error>
+    where
+        F: Fn(&BusRef<E, V>, Option<&V>) + IntoHandler<Handler<E, V>>,
+    {
+        let id = self.bus.on(event, f)?;
+        Ok(Subscription {
+            bus: Rc::downgrade(&self.bus),
+            id,
+        })
+    }
+
+    /// Emits an `event` with a `value` associated to it like
+    /// `emit_with_value` does, reporting whether a listener halted it with
+    /// `BusRef::stop_propagation` before it reached every listener.
+    pub fn emit_controlled(&self, event: E, value: Option<&V>) -> Result<ControlFlow<()>, Error> {
+        self.bus.emit_controlled(event, value)
+    }
+
+    /// Adds a listener for `event` that will call `f` with a mutable
+    /// reference to the value of every `emit_mut` of said event.
+    pub fn on_mut<F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&BusRef<E, V>, &mut V) + 'static,
+    {
+        self.bus.on_mut(event, f)
+    }
+
+    /// Emits an `event` with a `value` that listeners added with `on_mut`
+    /// can modify. Once this returns `value` holds the outcome of all of
+    /// them.
+    pub fn emit_mut(&self, event: E, value: &mut V) -> Result<(), Error> {
+        self.bus.emit_mut(event, value)
+    }
+
+    /// Adds a listener for `event` that can fail, whose errors are
+    /// reported by `try_emit`.
+    pub fn try_on<F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&BusRef<E, V>, Option<&V>) -> Result<(), BoxError> + 'static,
+    {
+        self.bus.try_on(event, f)
+    }
+
+    /// Emits an `event` with a `value` associated to it, gathering the
+    /// errors of the listeners added with `try_on`.
+    pub fn try_emit(&self, event: E, value: Option<&V>) -> Result<(), EmitError<E>> {
+        self.bus.try_emit(event, value)
+    }
+
+    /// Adds a listener for `event` whose return value is gathered by
+    /// `emit_collect`.
+    pub fn on_collect<R, F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        R: 'static,
+        F: Fn(&BusRef<E, V>, Option<&V>) -> R + 'static,
+    {
+        self.bus.on_collect(event, f)
+    }
+
+    /// Emits an `event` with a `value` associated to it, returning the
+    /// values of type `R` returned by the listeners added with `on_collect`,
+    /// in the order they were invoked, or an error if one of them returns
+    /// another type.
+    pub fn emit_collect<R: 'static>(&self, event: E, value: Option<&V>) -> Result<Vec<R>, Error> {
+        self.bus.emit_collect(event, value)
+    }
+
+    /// Adds a listener that will call `f` for every event emitted on this
+    /// bus, along with the event itself.
+    pub fn on_any<F>(&self, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&BusRef<E, V>, &E, Option<&V>) + 'static,
+    {
+        self.bus.on_any(f)
+    }
+
+    /// Adds a listener that will call `f` whenever an event of the same
+    /// enum variant as `variant` is fired, along with the emitted event.
+    ///
+    /// `E` must be an enum, see [`BusRef::on_variant`].
+    pub fn on_variant<F>(&self, variant: E, f: F) -> Result<SubscriptionId, Error>
     where
+        F: Fn(&BusRef<E, V>, &E, Option<&V>) + 'static,
+    {
+        self.bus.on_variant(variant, f)
+    }
+
+    /// Adds a listener for `event` that will call `f` when said event is
+    /// fired, but only if `predicate` accepts the emitted value.
+    pub fn on_filtered<P, F>(&self, event: E, predicate: P, f: F) -> Result<SubscriptionId, Error>
+    where
+        P: Fn(&E, Option<&V>) -> bool + 'static,
         F: Fn(&BusRef<E, V>, Option<&V>) + 'static,
     {
-        // if let Ok(bus_lock) = self.bus.try_borrow_mut() {
-        //     bus_lock.on(event, f)
-        // } else {
-        //     Err(Error::BusLock)
-        // }
+        self.bus.on_filtered(event, predicate, f)
+    }
+
+    /// Like `on_any`, but `f` is only called for the events and values
+    /// accepted by `predicate`.
+    pub fn on_any_filtered<P, F>(&self, predicate: P, f: F) -> Result<SubscriptionId, Error>
+    where
+        P: Fn(&E, Option<&V>) -> bool + 'static,
+        F: Fn(&BusRef<E, V>, &E, Option<&V>) + 'static,
+    {
+        self.bus.on_any_filtered(predicate, f)
+    }
+}
+
+impl<E> EventBus<E::Kind, E>
+where
+    E: Event + 'static,
+{
+    /// Adds a listener that will call `f` with the payload of every event
+    /// of the given `variant` fired on this bus with `emit_event`.
+    pub fn on_event<T, F>(&self, variant: T, f: F) -> Result<SubscriptionId, Error>
+    where
+        T: Variant<E> + 'static,
+        F: Fn(&BusRef<E::Kind, E>, &T::Payload) + 'static,
+    {
+        self.bus.on_event(variant, f)
+    }
+
+    /// Emits `event` under its kind, passing it along as the value of the
+    /// emit.
+    pub fn emit_event(&self, event: E) -> Result<(), Error> {
+        self.bus.emit_event(event)
+    }
+}
+
+impl<E, V> EventEmitter<E, V> for EventBus<E, V>
+where
+    E: Eq + Hash,
+{
+    type Ref = BusRef<E, V>;
+    type Handler = Handler<E, V>;
+
+    fn on<F>(&self, event: E, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&BusRef<E, V>, Option<&V>) + IntoHandler<Handler<E, V>>,
+    {
         self.bus.on(event, f)
     }
 
+    fn on_with_priority<F>(&self, event: E, priority: i32, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&BusRef<E, V>, Option<&V>) + IntoHandler<Handler<E, V>>,
+    {
+        self.bus.on_with_priority(event, priority, f)
+    }
+
+    fn on_n<F>(&self, event: E, n: usize, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&BusRef<E, V>, Option<&V>) + IntoHandler<Handler<E, V>>,
+    {
+        self.bus.on_n(event, n, f)
+    }
+
+    fn off(&self, subscription: SubscriptionId) -> Result<bool, Error> {
+        self.bus.off(subscription)
+    }
+
     fn emit(&self, event: E) -> Result<(), Error> {
         self.emit_with_value(event, None)
     }
 
     fn emit_with_value(&self, event: E, value: Option<&V>) -> Result<(), Error> {
-        // if let Ok(bus_lock) = self.bus.try_borrow() {
-        //     bus_lock.emit_with_value(event, value)
-        // } else {
-        //     Err(Error::BusLock)
-        // }
         self.bus.emit_with_value(event, value)
     }
 }
@@ -109,11 +380,354 @@ impl<E, V> Clone for EventBus<E, V> {
     }
 }
 
+/// A guard that removes its listener from the bus when dropped.
+///
+/// It only holds a weak reference to the bus, so it doesn't keep the bus
+/// alive: if the bus is gone by the time the guard is dropped there is
+/// nothing left to do.
+#[must_use = "dropping a `Subscription` immediately removes its listener"]
+pub struct Subscription<E, V> {
+    bus: Weak<BusRef<E, V>>,
+    id: SubscriptionId,
+}
+
+impl<E, V> Subscription<E, V> {
+    /// The id of the guarded listener
+    pub fn id(&self) -> SubscriptionId {
+        self.id
+    }
+
+    /// Releases the guard without removing the listener, returning its id
+    pub fn detach(mut self) -> SubscriptionId {
+        self.bus = Weak::new();
+        self.id
+    }
+}
+
+impl<E, V> Drop for Subscription<E, V> {
+    fn drop(&mut self) {
+        if let Some(bus) = self.bus.upgrade() {
+            let _ = bus.remove_listener(self.id);
+        }
+    }
+}
+
+/// A listener as stored by a `TopicBus`
+type TopicHandler<V> = dyn Fn(&TopicBus<V>, &str, Option<&V>);
+
+type TopicListeners<V> = Trie<Listener<Rc<TopicHandler<V>>, Cell<usize>>>;
+
+struct TopicBusRef<V> {
+    listeners: RefCell<TopicListeners<V>>,
+    next_id: Cell<usize>,
+    emit_count: Cell<usize>,
+}
+
+/// An event bus whose events are hierarchical topics. If you need to share
+/// the bus across threads use `sync::TopicBus`.
+///
+/// Listeners subscribe to patterns made of dot separated segments, where `*`
+/// matches exactly one segment and `#` matches zero or more of them (see the
+/// `topic` module). Emitting a topic invokes every listener whose pattern
+/// matches it, in registration order.
+///
+/// # Example
+///
+/// ```
+/// use tram::unsync::TopicBus;
+/// use std::{cell::RefCell, rc::Rc};
+///
+/// let bus: TopicBus<u32> = TopicBus::new();
+/// let shipped = Rc::new(RefCell::new(Vec::new()));
+/// let shipped_closure = Rc::clone(&shipped);
+///
+/// bus.on("order.#.shipped", move |_bus, topic, order_id| {
+///     shipped_closure.borrow_mut().push((topic.to_owned(), *order_id.unwrap()));
+/// })
+/// .expect("Failed to subscribe");
+///
+/// bus.emit_with_value("order.eu.shipped", Some(&1)).expect("Failed to emit");
+/// bus.emit_with_value("order.created", Some(&2)).expect("Failed to emit");
+///
+/// assert_eq!(*shipped.borrow(), vec![("order.eu.shipped".to_owned(), 1)]);
+/// ```
+pub struct TopicBus<V> {
+    inner: Rc<TopicBusRef<V>>,
+}
+
+impl<V> TopicBus<V> {
+    pub fn new() -> Self {
+        Self {
+            inner: Rc::new(TopicBusRef {
+                listeners: RefCell::new(Trie::new()),
+                next_id: Cell::new(0),
+                emit_count: Cell::new(0),
+            }),
+        }
+    }
+
+    /// The current number of topics emitted on this bus
+    pub fn event_count(&self) -> usize {
+        self.inner.emit_count.get()
+    }
+
+    /// Adds a listener that will call `f` with the emitted topic and value
+    /// whenever a topic matching `pattern` is emitted.
+    pub fn on<F>(&self, pattern: &str, f: F) -> Result<SubscriptionId, Error>
+    where
+        F: Fn(&Self, &str, Option<&V>) + 'static,
+    {
+        let pattern = Pattern::parse(pattern)?;
+        let id = SubscriptionId::new(self.inner.next_id.replace(self.inner.next_id.get() + 1));
+        let listener = Listener::new(id, Rc::new(f) as Rc<TopicHandler<V>>, 0, None);
+
+        let mut listeners = self
+            .inner
+            .listeners
+            .try_borrow_mut()
+            .map_err(|_| Error::Reentrant)?;
+        listeners.insert(&pattern, listener);
+        Ok(id)
+    }
+
+    /// Removes the listener identified by `subscription`.
+    ///
+    /// Returns `false` if no such listener was registered on this bus.
+    pub fn off(&self, subscription: SubscriptionId) -> Result<bool, Error> {
+        let mut listeners = self
+            .inner
+            .listeners
+            .try_borrow_mut()
+            .map_err(|_| Error::Reentrant)?;
+        Ok(listeners.remove_where(&|l| l.id == subscription))
+    }
+
+    /// Emits `topic`, firing all listeners whose pattern matches it.
+    ///
+    /// When used this way the value passed to listeners will always be `None`.
+    pub fn emit(&self, topic: &str) -> Result<(), Error> {
+        self.emit_with_value(topic, None)
+    }
+
+    /// Emits `topic` with a `value` associated to it, firing all listeners
+    /// whose pattern matches it.
+    ///
+    /// Emitted topics can't contain wildcards. Listeners added or removed
+    /// while the topic is being dispatched don't affect this emit.
+    pub fn emit_with_value(&self, topic: &str, value: Option<&V>) -> Result<(), Error> {
+        let segments = split_topic(topic)?;
+
+        let mut handlers = {
+            let listeners = self
+                .inner
+                .listeners
+                .try_borrow()
+                .map_err(|_| Error::Reentrant)?;
+            listeners
+                .matches(&segments)
+                .into_iter()
+                .map(|l| (l.id, Rc::clone(&l.handler)))
+                .collect::<Vec<_>>()
+        };
+        handlers.sort_by_key(|(id, _)| *id);
+
+        self.inner.emit_count.set(self.inner.emit_count.get() + 1);
+        handlers
+            .iter()
+            .for_each(|(_, handler)| handler(self, topic, value));
+
+        Ok(())
+    }
+}
+
+impl<V> Default for TopicBus<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Clone for TopicBus<V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Rc::clone(&self.inner),
+        }
+    }
+}
+
+/// A listener as stored by a `TypedBus`, receiving the emitted event as a
+/// `dyn Any` it downcasts to the type it was registered for
+type TypedHandler = dyn Fn(&TypedBus, &dyn Any);
+
+type TypedListeners = HashMap<TypeId, Vec<Listener<Rc<TypedHandler>, Cell<usize>>>>;
+
+struct TypedBusRef {
+    listeners: RefCell<TypedListeners>,
+    next_id: Cell<usize>,
+    emit_count: Cell<usize>,
+}
+
+/// An event bus where each event is its own Rust type. If you need to share
+/// the bus across threads use `sync::TypedBus`.
+///
+/// Listeners are registered for an event type `T` and receive a `&T` when a
+/// value of that type is emitted, so each event carries its own payload
+/// instead of sharing a single value type with every other event. Listeners
+/// for the same type are invoked in registration order.
+///
+/// # Example
+///
+/// ```
+/// use tram::unsync::TypedBus;
+/// use std::{cell::RefCell, rc::Rc};
+///
+/// struct UserLoggedIn {
+///     name: String,
+/// }
+///
+/// struct Shutdown;
+///
+/// let bus = TypedBus::new();
+/// let log = Rc::new(RefCell::new(Vec::new()));
+///
+/// let log_closure = Rc::clone(&log);
+/// bus.on(move |_bus, event: &UserLoggedIn| {
+///     log_closure.borrow_mut().push(format!("{} logged in", event.name));
+/// })
+/// .expect("Failed to listen for this event");
+///
+/// let log_closure = Rc::clone(&log);
+/// bus.on(move |_bus, _: &Shutdown| {
+///     log_closure.borrow_mut().push("shutdown".to_owned());
+/// })
+/// .expect("Failed to listen for this event");
+///
+/// bus.emit(UserLoggedIn { name: "ada".to_owned() }).expect("Failed to emit");
+/// bus.emit(Shutdown).expect("Failed to emit");
+///
+/// assert_eq!(*log.borrow(), vec!["ada logged in", "shutdown"]);
+/// ```
+pub struct TypedBus {
+    inner: Rc<TypedBusRef>,
+}
+
+impl TypedBus {
+    pub fn new() -> Self {
+        Self {
+            inner: Rc::new(TypedBusRef {
+                listeners: RefCell::new(HashMap::new()),
+                next_id: Cell::new(0),
+                emit_count: Cell::new(0),
+            }),
+        }
+    }
+
+    /// The current number of events emitted on this bus
+    pub fn event_count(&self) -> usize {
+        self.inner.emit_count.get()
+    }
+
+    /// Adds a listener that will call `f` whenever an event of type `T` is
+    /// emitted.
+    pub fn on<T, F>(&self, f: F) -> Result<SubscriptionId, Error>
+    where
+        T: 'static,
+        F: Fn(&Self, &T) + 'static,
+    {
+        let id = SubscriptionId::new(self.inner.next_id.replace(self.inner.next_id.get() + 1));
+        let handler = Rc::new(move |bus: &Self, event: &dyn Any| {
+            if let Some(event) = event.downcast_ref::<T>() {
+                f(bus, event)
+            }
+        });
+        let listener = Listener::new(id, handler as Rc<TypedHandler>, 0, None);
+
+        let mut listeners = self
+            .inner
+            .listeners
+            .try_borrow_mut()
+            .map_err(|_| Error::Reentrant)?;
+        listeners
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .push(listener);
+        Ok(id)
+    }
+
+    /// Removes the listener identified by `subscription`.
+    ///
+    /// Returns `false` if no such listener was registered on this bus.
+    pub fn off(&self, subscription: SubscriptionId) -> Result<bool, Error> {
+        let mut listeners = self
+            .inner
+            .listeners
+            .try_borrow_mut()
+            .map_err(|_| Error::Reentrant)?;
+        let mut removed = false;
+        listeners.retain(|_, list| {
+            let len = list.len();
+            list.retain(|l| l.id != subscription);
+            removed |= list.len() != len;
+            !list.is_empty()
+        });
+        Ok(removed)
+    }
+
+    /// Emits `event`, firing all listeners registered for its type.
+    ///
+    /// Listeners added or removed while the event is being dispatched don't
+    /// affect this emit.
+    pub fn emit<T: 'static>(&self, event: T) -> Result<(), Error> {
+        let handlers = {
+            let listeners = self
+                .inner
+                .listeners
+                .try_borrow()
+                .map_err(|_| Error::Reentrant)?;
+            listeners
+                .get(&TypeId::of::<T>())
+                .map(|list| {
+                    list.iter()
+                        .map(|l| Rc::clone(&l.handler))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+
+        self.inner.emit_count.set(self.inner.emit_count.get() + 1);
+        handlers.iter().for_each(|handler| handler(self, &event));
+
+        Ok(())
+    }
+}
+
+impl Default for TypedBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for TypedBus {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Rc::clone(&self.inner),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
-    use std::{cell::RefCell, rc::Rc};
+    use std::{
+        cell::{Cell, RefCell},
+        panic::{self, AssertUnwindSafe},
+        rc::Rc,
+        time::Duration,
+    };
+
+    use crate::limit::{
+        Admission, Clock, ManualClock, Overflow, PerEvent, RateLimit, SlidingWindow, Total,
+    };
 
     #[derive(PartialEq, Eq, Hash)]
     enum EventType {
@@ -234,4 +848,1116 @@ mod test {
         assert_eq!(*status.borrow(), None);
         assert_eq!(bus.event_count(), 2);
     }
+
+    #[test]
+    fn off() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let counter = Rc::new(RefCell::new(0));
+        let counter_closure = Rc::clone(&counter);
+        let counter_closure_2 = Rc::clone(&counter);
+
+        let subscription = bus
+            .on(EventType::Start, move |_, _| {
+                *counter_closure.borrow_mut() += 1;
+            })
+            .unwrap();
+
+        bus.on(EventType::Start, move |_, _| {
+            *counter_closure_2.borrow_mut() += 1;
+        })
+        .unwrap();
+
+        assert!(bus.off(subscription).unwrap());
+        assert!(!bus.off(subscription).unwrap());
+
+        bus.emit(EventType::Start).expect("Failed to emit");
+        assert_eq!(*counter.borrow(), 1);
+    }
+
+    #[test]
+    fn subscription_guard() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let counter = Rc::new(RefCell::new(0));
+        let counter_closure = Rc::clone(&counter);
+
+        let subscription = bus
+            .subscribe(EventType::Start, move |_, _| {
+                *counter_closure.borrow_mut() += 1;
+            })
+            .unwrap();
+
+        bus.emit(EventType::Start).expect("Failed to emit");
+        drop(subscription);
+        bus.emit(EventType::Start).expect("Failed to emit");
+
+        assert_eq!(*counter.borrow(), 1);
+    }
+
+    #[test]
+    fn subscription_detach() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let counter = Rc::new(RefCell::new(0));
+        let counter_closure = Rc::clone(&counter);
+
+        let subscription = bus
+            .subscribe(EventType::Start, move |_, _| {
+                *counter_closure.borrow_mut() += 1;
+            })
+            .unwrap();
+
+        let id = subscription.detach();
+        bus.emit(EventType::Start).expect("Failed to emit");
+        assert_eq!(*counter.borrow(), 1);
+
+        assert!(bus.off(id).unwrap());
+    }
+
+    #[test]
+    fn subscription_outlives_bus() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let subscription = bus.subscribe(EventType::Start, |_, _| {}).unwrap();
+        let weak_bus = Rc::downgrade(&bus.bus);
+
+        drop(bus);
+        assert!(weak_bus.upgrade().is_none());
+        drop(subscription);
+    }
+
+    #[test]
+    fn once() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let counter = Rc::new(RefCell::new(0));
+        let counter_closure = Rc::clone(&counter);
+
+        let subscription = bus
+            .once(EventType::Start, move |_, _| {
+                *counter_closure.borrow_mut() += 1;
+            })
+            .unwrap();
+
+        bus.emit(EventType::Start).expect("Failed to emit");
+        bus.emit(EventType::Start).expect("Failed to emit");
+
+        assert_eq!(*counter.borrow(), 1);
+        assert!(!bus.off(subscription).unwrap());
+    }
+
+    #[test]
+    fn on_n() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let counter = Rc::new(RefCell::new(0));
+        let counter_closure = Rc::clone(&counter);
+
+        bus.on_n(EventType::Start, 3, move |_, _| {
+            *counter_closure.borrow_mut() += 1;
+        })
+        .unwrap();
+
+        for _ in 0..5 {
+            bus.emit(EventType::Start).expect("Failed to emit");
+        }
+        assert_eq!(
+            bus.on_n(EventType::Start, 0, |_, _| {}),
+            Err(Error::InvalidLimit)
+        );
+
+        assert_eq!(*counter.borrow(), 3);
+    }
+
+    #[test]
+    fn once_re_emit() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let counter = Rc::new(RefCell::new(0));
+        let counter_closure = Rc::clone(&counter);
+
+        let subscription = bus
+            .once(EventType::Start, move |inner_bus, _| {
+                *counter_closure.borrow_mut() += 1;
+                inner_bus
+                    .emit(EventType::Start)
+                    .expect("Cannot emit START event");
+            })
+            .unwrap();
+
+        bus.emit(EventType::Start).expect("Failed to emit");
+
+        assert_eq!(*counter.borrow(), 1);
+        assert_eq!(bus.event_count(), 2);
+        assert!(!bus.off(subscription).unwrap());
+    }
+
+    #[test]
+    fn priorities() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        for (priority, tag) in [
+            (0, "business"),
+            (10, "audit"),
+            (0, "business 2"),
+            (20, "validation"),
+        ] {
+            let order_closure = Rc::clone(&order);
+            bus.on_with_priority(EventType::Start, priority, move |_, _| {
+                order_closure.borrow_mut().push(tag);
+            })
+            .unwrap();
+        }
+
+        let order_closure = Rc::clone(&order);
+        bus.on(EventType::Start, move |_, _| {
+            order_closure.borrow_mut().push("default");
+        })
+        .unwrap();
+
+        bus.emit(EventType::Start).expect("Failed to emit");
+
+        assert_eq!(
+            *order.borrow(),
+            vec!["validation", "audit", "business", "business 2", "default"]
+        );
+    }
+
+    #[test]
+    fn on_during_emit() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let order = Rc::new(RefCell::new(Vec::new()));
+        let order_closure = Rc::clone(&order);
+
+        bus.once(EventType::Start, move |inner_bus, _| {
+            let order_closure = Rc::clone(&order_closure);
+            inner_bus
+                .on(EventType::Stop, move |_, _| {
+                    order_closure.borrow_mut().push("stop");
+                })
+                .expect("Cannot register STOP listener");
+
+            // The new listener isn't live until this emit completes
+            inner_bus
+                .emit(EventType::Stop)
+                .expect("Cannot emit STOP event");
+        })
+        .unwrap();
+
+        bus.emit(EventType::Start).expect("Failed to emit");
+        assert!(order.borrow().is_empty());
+
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert_eq!(*order.borrow(), vec!["stop"]);
+    }
+
+    #[test]
+    fn off_during_emit() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let counter = Rc::new(RefCell::new(0));
+        let counter_closure = Rc::clone(&counter);
+        let target = Rc::new(Cell::new(None));
+        let target_closure = Rc::clone(&target);
+
+        bus.on_with_priority(EventType::Start, 1, move |inner_bus, _| {
+            if let Some(subscription) = target_closure.take() {
+                assert!(inner_bus.off(subscription).unwrap());
+                assert!(!inner_bus.off(subscription).unwrap());
+            }
+        })
+        .unwrap();
+
+        let subscription = bus
+            .on(EventType::Start, move |_, _| {
+                *counter_closure.borrow_mut() += 1;
+            })
+            .unwrap();
+        target.set(Some(subscription));
+
+        // The removed listener still receives the event being dispatched
+        bus.emit(EventType::Start).expect("Failed to emit");
+        bus.emit(EventType::Start).expect("Failed to emit");
+
+        assert_eq!(*counter.borrow(), 1);
+    }
+
+    #[test]
+    fn on_any() {
+        let bus: EventBus<u8, u8> = EventBus::unbound();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let log_closure = Rc::clone(&log);
+        bus.on(1, move |_, value| {
+            log_closure
+                .borrow_mut()
+                .push((1, value.copied(), "specific"));
+        })
+        .unwrap();
+
+        let log_closure = Rc::clone(&log);
+        let subscription = bus
+            .on_any(move |_, event, value| {
+                log_closure
+                    .borrow_mut()
+                    .push((*event, value.copied(), "any"));
+            })
+            .unwrap();
+
+        let log_closure = Rc::clone(&log);
+        bus.on(1, move |_, value| {
+            log_closure
+                .borrow_mut()
+                .push((1, value.copied(), "specific 2"));
+        })
+        .unwrap();
+
+        bus.emit_with_value(1, Some(&10)).expect("Failed to emit");
+        bus.emit(2).expect("Failed to emit");
+        assert!(bus.off(subscription).unwrap());
+        bus.emit(3).expect("Failed to emit");
+
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                (1, Some(10), "specific"),
+                (1, Some(10), "any"),
+                (1, Some(10), "specific 2"),
+                (2, None, "any"),
+            ]
+        );
+    }
+
+    #[test]
+    fn on_filtered() {
+        let bus: EventBus<u8, u8> = EventBus::unbound();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let log_closure = Rc::clone(&log);
+        bus.on_filtered(
+            1,
+            |_, value| value.is_some_and(|v| *v > 10),
+            move |_, value| {
+                log_closure.borrow_mut().push((1, value.copied()));
+            },
+        )
+        .unwrap();
+
+        let log_closure = Rc::clone(&log);
+        bus.on_any_filtered(
+            |event, _| *event != 1,
+            move |_, event, value| {
+                log_closure.borrow_mut().push((*event, value.copied()));
+            },
+        )
+        .unwrap();
+
+        bus.emit_with_value(1, Some(&5)).expect("Failed to emit");
+        bus.emit_with_value(1, Some(&50)).expect("Failed to emit");
+        bus.emit(1).expect("Failed to emit");
+        bus.emit_with_value(2, Some(&5)).expect("Failed to emit");
+
+        assert_eq!(*log.borrow(), vec![(1, Some(50)), (2, Some(5))]);
+    }
+
+    #[test]
+    fn emit_mut() {
+        let bus: EventBus<EventType, String> = EventBus::unbound();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        bus.on_mut(EventType::Start, |_, value| value.push_str(" world"))
+            .unwrap();
+
+        let log_closure = Rc::clone(&log);
+        bus.on(EventType::Start, move |_, value| {
+            log_closure.borrow_mut().push(value.cloned());
+        })
+        .unwrap();
+
+        bus.on_mut(EventType::Start, |_, value| {
+            *value = value.to_uppercase();
+        })
+        .unwrap();
+
+        let mut value = "hello".to_owned();
+        bus.emit_mut(EventType::Start, &mut value)
+            .expect("Failed to emit");
+        bus.emit_with_value(EventType::Start, Some(&"untouched".to_owned()))
+            .expect("Failed to emit");
+
+        assert_eq!(value, "HELLO WORLD");
+        assert_eq!(
+            *log.borrow(),
+            vec![Some("hello world".to_owned()), Some("untouched".to_owned())]
+        );
+        assert_eq!(bus.event_count(), 2);
+    }
+
+    #[test]
+    fn emit_collect() {
+        let bus: EventBus<EventType, u8> = EventBus::unbound();
+
+        bus.on_collect(EventType::Stop, |_, pending| pending.is_none())
+            .unwrap();
+        bus.on_with_priority(EventType::Stop, 1, |_, _| {}).unwrap();
+        bus.on_collect(EventType::Stop, |_, pending| {
+            pending.is_some_and(|p| *p < 5)
+        })
+        .unwrap();
+
+        let votes: Vec<bool> = bus
+            .emit_collect(EventType::Stop, Some(&3))
+            .expect("Failed to emit");
+        assert_eq!(votes, vec![false, true]);
+
+        let votes: Vec<bool> = bus
+            .emit_collect(EventType::Start, None)
+            .expect("Failed to emit");
+        assert!(votes.is_empty());
+
+        let id = bus
+            .on_collect(EventType::Stop, |_, _| "not a vote")
+            .unwrap();
+        assert_eq!(
+            bus.emit_collect::<bool>(EventType::Stop, None),
+            Err(Error::ReturnTypeMismatch { subscription: id })
+        );
+
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert_eq!(bus.event_count(), 4);
+    }
+
+    #[test]
+    fn stop_propagation() {
+        let bus: EventBus<u8, bool> = EventBus::unbound();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let log_closure = Rc::clone(&log);
+        bus.on_with_priority(1, 10, move |inner_bus, handled| {
+            log_closure.borrow_mut().push("first");
+            if handled == Some(&true) {
+                inner_bus.stop_propagation();
+            }
+        })
+        .unwrap();
+
+        let log_closure = Rc::clone(&log);
+        bus.on(1, move |_, _| {
+            log_closure.borrow_mut().push("second");
+        })
+        .unwrap();
+
+        assert_eq!(
+            bus.emit_controlled(1, Some(&true)),
+            Ok(ControlFlow::Break(()))
+        );
+        assert_eq!(
+            bus.emit_controlled(1, Some(&false)),
+            Ok(ControlFlow::Continue(()))
+        );
+
+        assert_eq!(*log.borrow(), vec!["first", "first", "second"]);
+    }
+
+    #[test]
+    fn stop_propagation_after_panic() {
+        let bus: EventBus<u8, ()> = EventBus::unbound();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        bus.on(2, |_, _| panic!("Failed to handle 2")).unwrap();
+
+        bus.on_with_priority(1, 10, |inner_bus, _| {
+            inner_bus.stop_propagation();
+            let panicked = panic::catch_unwind(AssertUnwindSafe(|| inner_bus.emit(2)));
+            assert!(panicked.is_err());
+        })
+        .unwrap();
+
+        let log_closure = Rc::clone(&log);
+        bus.on(1, move |_, _| {
+            log_closure.borrow_mut().push("second");
+        })
+        .unwrap();
+
+        assert_eq!(bus.emit_controlled(1, None), Ok(ControlFlow::Break(())));
+        assert!(log.borrow().is_empty());
+    }
+
+    #[test]
+    fn stop_propagation_re_emit() {
+        let bus: EventBus<u8, ()> = EventBus::unbound();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        bus.on(1, |inner_bus, _| {
+            inner_bus.emit(2).expect("Cannot emit 2");
+        })
+        .unwrap();
+
+        bus.on(2, |inner_bus, _| inner_bus.stop_propagation())
+            .unwrap();
+
+        let log_closure = Rc::clone(&log);
+        bus.on_any(move |_, event, _| {
+            log_closure.borrow_mut().push(*event);
+        })
+        .unwrap();
+
+        assert_eq!(bus.emit_controlled(1, None), Ok(ControlFlow::Continue(())));
+        assert_eq!(*log.borrow(), vec![1]);
+    }
+
+    #[test]
+    fn try_emit() {
+        let bus: EventBus<EventType, &str> = EventBus::unbound();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let first = bus
+            .try_on(EventType::Start, |_, value| {
+                value.unwrap().parse::<u8>()?;
+                Ok(())
+            })
+            .unwrap();
+
+        let log_closure = Rc::clone(&log);
+        bus.on(EventType::Start, move |_, value| {
+            log_closure.borrow_mut().push(value.copied());
+        })
+        .unwrap();
+
+        let second = bus
+            .try_on(EventType::Start, |_, _| Err("always fails".into()))
+            .unwrap();
+
+        assert!(bus.try_emit(EventType::Stop, Some(&"x")).is_ok());
+
+        match bus.try_emit(EventType::Start, Some(&"x")) {
+            Err(EmitError::Listeners { event, failures }) => {
+                assert!(event == EventType::Start);
+                let ids = failures.iter().map(|f| f.subscription).collect::<Vec<_>>();
+                assert_eq!(ids, vec![first, second]);
+                assert_eq!(failures[1].error.to_string(), "always fails");
+            }
+            _ => panic!("Listener failures were not reported"),
+        }
+
+        bus.set_stop_on_error(true);
+        match bus.try_emit(EventType::Start, Some(&"y")) {
+            Err(EmitError::Listeners { failures, .. }) => assert_eq!(failures.len(), 1),
+            _ => panic!("Listener failures were not reported"),
+        }
+
+        let errors = Rc::new(RefCell::new(Vec::new()));
+        let errors_closure = Rc::clone(&errors);
+        bus.on_error(move |error| {
+            if let Error::ListenerFailed { subscription, .. } = error {
+                errors_closure.borrow_mut().push(*subscription);
+            }
+        })
+        .unwrap();
+
+        bus.emit_with_value(EventType::Start, Some(&"z"))
+            .expect("Failed to emit");
+        assert_eq!(*errors.borrow(), vec![first, second]);
+
+        assert_eq!(*log.borrow(), vec![Some("x"), Some("z")]);
+    }
+
+    #[test]
+    fn catch_panics() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let log_closure = Rc::clone(&log);
+        bus.on_error(move |error| {
+            log_closure.borrow_mut().push(format!("{:?}", error));
+        })
+        .unwrap();
+        bus.set_catch_panics(true);
+
+        let faulty = bus
+            .on(EventType::Start, |_, _| panic!("broken plugin"))
+            .unwrap();
+
+        let log_closure = Rc::clone(&log);
+        bus.on(EventType::Start, move |_, _| {
+            log_closure.borrow_mut().push("second listener".to_owned());
+        })
+        .unwrap();
+
+        bus.emit(EventType::Start).expect("Failed to emit");
+
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                format!(
+                    "{:?}",
+                    Error::ListenerPanicked {
+                        subscription: faulty,
+                        message: "broken plugin".to_owned()
+                    }
+                ),
+                "second listener".to_owned()
+            ]
+        );
+        assert_eq!(bus.event_count(), 1);
+    }
+
+    #[test]
+    fn limit_errors() {
+        fn emit_twice(bus: &EventBus<EventType, ()>) -> Result<(), Box<dyn std::error::Error>> {
+            bus.emit(EventType::Start)?;
+            bus.emit(EventType::Stop)?;
+            Ok(())
+        }
+
+        let bus: EventBus<EventType, ()> = EventBus::bound(3);
+        assert!(emit_twice(&bus).is_ok());
+
+        let error = emit_twice(&bus).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<Error>(),
+            Some(&Error::Disconnected { limit: 3, count: 3 })
+        );
+        assert_eq!(
+            error.to_string(),
+            "the bus has reached its limit of 3 emits (3 emitted)"
+        );
+    }
+
+    #[test]
+    fn dead_letters() {
+        let bus: EventBus<&str, u8> = EventBus::unbound();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let log_closure = Rc::clone(&log);
+        bus.on_unhandled(move |_, event, value| {
+            log_closure
+                .borrow_mut()
+                .push((event.to_string(), value.copied()));
+        })
+        .unwrap();
+
+        bus.on("start", |_, _| {}).unwrap();
+        let once = bus.once("stop", |_, _| {}).unwrap();
+
+        bus.emit_with_value("start", Some(&1))
+            .expect("Failed to emit");
+        bus.emit_with_value("strat", Some(&2))
+            .expect("Failed to emit");
+        bus.emit("stop").expect("Failed to emit");
+        bus.emit("stop").expect("Failed to emit");
+        assert!(!bus.off(once).unwrap());
+
+        assert_eq!(
+            *log.borrow(),
+            vec![("strat".to_owned(), Some(2)), ("stop".to_owned(), None)]
+        );
+        assert_eq!(bus.unhandled_count(), 2);
+        assert_eq!(bus.event_count(), 4);
+    }
+
+    #[test]
+    fn dead_letters_of_skipped_emits() {
+        let bus: EventBus<&str, u8> = EventBus::unbound();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let log_closure = Rc::clone(&log);
+        bus.on_unhandled(move |_, event, value| {
+            log_closure
+                .borrow_mut()
+                .push((event.to_string(), value.copied()));
+        })
+        .unwrap();
+
+        bus.on_filtered("start", |_, value| value == Some(&1), |_, _| {})
+            .unwrap();
+        bus.on_mut("stop", |_, value| *value += 1).unwrap();
+
+        bus.emit_with_value("start", Some(&1))
+            .expect("Failed to emit");
+        bus.emit_with_value("start", Some(&2))
+            .expect("Failed to emit");
+        bus.emit_mut("stop", &mut 3).expect("Failed to emit");
+        bus.emit_with_value("stop", Some(&4))
+            .expect("Failed to emit");
+
+        assert_eq!(
+            *log.borrow(),
+            vec![("start".to_owned(), Some(2)), ("stop".to_owned(), Some(4))]
+        );
+        assert_eq!(bus.unhandled_count(), 2);
+    }
+
+    #[test]
+    fn event_limits() {
+        let limit = PerEvent::new().limit("init", 1).limit("retry", 2);
+        let bus: EventBus<&str, ()> = EventBus::with_limit(limit);
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        for event in ["init", "retry", "tick"] {
+            let log_closure = Rc::clone(&log);
+            bus.on(event, move |_, _| {
+                log_closure.borrow_mut().push(event);
+            })
+            .unwrap();
+        }
+
+        bus.emit("init").expect("Failed to emit");
+        assert_eq!(
+            bus.emit("init"),
+            Err(Error::EventDisconnected { limit: 1, count: 1 })
+        );
+        assert!(bus.event_disconnected(&"init"));
+
+        bus.emit("retry").expect("Failed to emit");
+        bus.emit("tick").expect("Failed to emit");
+        bus.emit("retry").expect("Failed to emit");
+        assert!(bus.emit("retry").is_err());
+        bus.emit("tick").expect("Failed to emit");
+
+        assert_eq!(
+            *log.borrow(),
+            vec!["init", "retry", "tick", "retry", "tick"]
+        );
+        assert!(!bus.event_disconnected(&"tick"));
+        assert!(!bus.disconnected());
+        assert_eq!(bus.event_count(), 5);
+    }
+
+    #[test]
+    fn limit_policies() {
+        let bus: EventBus<EventType, ()> = EventBus::bound(0);
+        assert!(bus.disconnected());
+        assert_eq!(
+            bus.emit(EventType::Start),
+            Err(Error::Disconnected { limit: 0, count: 0 })
+        );
+
+        let bus: EventBus<EventType, ()> =
+            EventBus::with_limit(PerEvent::new().limit(EventType::Start, 1));
+        bus.emit(EventType::Start).expect("Failed to emit");
+        assert!(bus.emit(EventType::Start).is_err());
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert!(!bus.disconnected());
+
+        let clock = ManualClock::new();
+        let window = Duration::from_secs(1);
+        let limit = SlidingWindow::with_clock(2, window, clock.clone());
+        let bus: EventBus<EventType, ()> = EventBus::with_limit(limit);
+        bus.emit(EventType::Start).expect("Failed to emit");
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert_eq!(
+            bus.emit(EventType::Start),
+            Err(Error::Throttled { limit: 2, window })
+        );
+        clock.advance(window / 2);
+        assert!(bus.emit(EventType::Start).is_err());
+        clock.advance(window / 2);
+        bus.emit(EventType::Start).expect("Failed to emit");
+        assert_eq!(bus.event_count(), 3);
+    }
+
+    #[test]
+    fn combined_limit_policies() {
+        let limit = (Total::new(3), PerEvent::new().limit(EventType::Stop, 1));
+        let bus: EventBus<EventType, ()> = EventBus::with_limit(limit);
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let log_closure = Rc::clone(&log);
+        bus.on(EventType::Stop, move |_, _| {
+            log_closure.borrow_mut().push("stopped");
+        })
+        .unwrap();
+
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert_eq!(
+            bus.emit(EventType::Stop),
+            Err(Error::EventDisconnected { limit: 1, count: 1 })
+        );
+        assert!(bus.event_disconnected(&EventType::Stop));
+        assert!(!bus.disconnected());
+
+        // The refused emit of `Stop` doesn't count against the total
+        bus.emit(EventType::Start).expect("Failed to emit");
+        bus.emit(EventType::Start).expect("Failed to emit");
+        assert_eq!(
+            bus.emit(EventType::Start),
+            Err(Error::Disconnected { limit: 3, count: 3 })
+        );
+        assert!(bus.disconnected());
+
+        bus.reset().unwrap();
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert_eq!(*log.borrow(), vec!["stopped", "stopped"]);
+        assert_eq!(bus.event_count(), 1);
+    }
+
+    #[test]
+    fn custom_limit_policy() {
+        /// Only lets `Stop` through once `Start` has been emitted
+        struct StartFirst(Cell<bool>);
+
+        impl EmitLimit<EventType> for StartFirst {
+            fn acquire(&self, event: &EventType) -> Result<Admission, Error> {
+                match event {
+                    EventType::Start => {
+                        self.0.set(true);
+                        Ok(Admission::Emit)
+                    }
+                    EventType::Stop if self.0.get() => Ok(Admission::Emit),
+                    EventType::Stop => Err(Error::Rejected {
+                        reason: "Stop before Start".to_owned(),
+                    }),
+                }
+            }
+        }
+
+        let bus: EventBus<EventType, ()> = EventBus::with_limit(StartFirst(Cell::new(false)));
+        let error = bus.emit(EventType::Stop).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the emit was rejected: Stop before Start"
+        );
+        bus.emit(EventType::Start).expect("Failed to emit");
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert_eq!(bus.event_count(), 2);
+    }
+
+    #[test]
+    fn rate_limits() {
+        let clock = ManualClock::new();
+        let second = Duration::from_secs(1);
+        let limit = RateLimit::with_clock(clock.clone()).limit(EventType::Start, 2, second);
+        let bus: EventBus<EventType, ()> = EventBus::with_limit(limit);
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let log_closure = Rc::clone(&log);
+        bus.on_unhandled(move |_, _, _| {
+            log_closure.borrow_mut().push("unhandled");
+        })
+        .unwrap();
+
+        bus.emit(EventType::Start).expect("Failed to emit");
+        bus.emit(EventType::Start).expect("Failed to emit");
+        assert_eq!(
+            bus.emit(EventType::Start),
+            Err(Error::RateLimited {
+                limit: 2,
+                per: second
+            })
+        );
+        bus.emit(EventType::Stop).expect("Failed to emit");
+
+        clock.advance(second / 2);
+        bus.emit(EventType::Start).expect("Failed to emit");
+        assert!(bus.emit(EventType::Start).is_err());
+        assert_eq!(bus.event_count(), 4);
+        assert_eq!(log.borrow().len(), 4);
+
+        let limit = RateLimit::with_clock(clock.clone())
+            .limit(EventType::Start, 2, second)
+            .overflow(Overflow::Drop);
+        let bus: EventBus<EventType, ()> = EventBus::with_limit(limit);
+        for _ in 0..3 {
+            bus.emit(EventType::Start).expect("Failed to emit");
+        }
+        assert_eq!(bus.event_count(), 2);
+        assert_eq!(bus.unhandled_count(), 2);
+
+        let started = clock.now();
+        let limit = RateLimit::with_clock(clock.clone())
+            .limit(EventType::Start, 2, second)
+            .overflow(Overflow::Defer);
+        let bus: EventBus<EventType, ()> = EventBus::with_limit(limit);
+        for _ in 0..4 {
+            bus.emit(EventType::Start).expect("Failed to emit");
+        }
+        assert_eq!(bus.event_count(), 4);
+        assert_eq!(clock.now() - started, second);
+
+        let limit = RateLimit::with_clock(clock.clone())
+            .limit(EventType::Start, 0, second)
+            .limit(EventType::Stop, 1, Duration::ZERO)
+            .overflow(Overflow::Defer);
+        let bus: EventBus<EventType, ()> = EventBus::with_limit(limit);
+        assert_eq!(
+            bus.emit(EventType::Start),
+            Err(Error::RateLimited {
+                limit: 0,
+                per: second
+            })
+        );
+        for _ in 0..3 {
+            bus.emit(EventType::Stop).expect("Failed to emit");
+        }
+        assert_eq!(bus.event_count(), 3);
+    }
+
+    #[test]
+    fn close() {
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let log_closure = Rc::clone(&log);
+        bus.on(EventType::Start, move |_, _| {
+            log_closure.borrow_mut().push("started");
+        })
+        .unwrap();
+
+        for name in ["cache", "socket"] {
+            let log_closure = Rc::clone(&log);
+            bus.on_close(move |bus| {
+                assert_eq!(bus.emit(EventType::Stop), Err(Error::Closed));
+                log_closure.borrow_mut().push(name);
+            })
+            .unwrap();
+        }
+
+        bus.emit(EventType::Start).expect("Failed to emit");
+        assert!(!bus.is_closed());
+        bus.close();
+        bus.close();
+
+        assert!(bus.is_closed());
+        assert_eq!(bus.emit(EventType::Start), Err(Error::Closed));
+        assert_eq!(bus.on(EventType::Stop, |_, _| {}), Err(Error::Closed));
+        assert_eq!(bus.on_close(|_| {}), Err(Error::Closed));
+        assert_eq!(bus.reset(), Err(Error::Closed));
+        assert_eq!(*log.borrow(), vec!["started", "cache", "socket"]);
+        assert_eq!(bus.event_count(), 1);
+
+        // The listeners and hooks holding the log have been dropped
+        assert_eq!(bus.listener_count(&EventType::Start), 0);
+        assert_eq!(Rc::strong_count(&log), 1);
+
+        let bus: EventBus<EventType, ()> = EventBus::unbound();
+        bus.on(EventType::Stop, |bus, _| bus.close()).unwrap();
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert_eq!(bus.listener_count(&EventType::Stop), 0);
+    }
+
+    #[test]
+    fn reset() {
+        let bus: EventBus<EventType, ()> = EventBus::bound(2);
+        bus.emit(EventType::Start).expect("Failed to emit");
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert!(bus.disconnected());
+
+        bus.reset().unwrap();
+        assert!(!bus.disconnected());
+        assert_eq!(bus.event_count(), 0);
+
+        let bus: EventBus<EventType, ()> =
+            EventBus::with_limit(PerEvent::new().limit(EventType::Stop, 1));
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert!(bus.event_disconnected(&EventType::Stop));
+
+        bus.reset().unwrap();
+        assert!(!bus.event_disconnected(&EventType::Stop));
+        assert_eq!(bus.event_count(), 0);
+
+        bus.emit(EventType::Stop).expect("Failed to emit");
+        assert!(bus.emit(EventType::Stop).is_err());
+        assert_eq!(bus.event_count(), 1);
+    }
+
+    #[test]
+    fn max_listeners() {
+        let bus: EventBus<&str, ()> = EventBus::unbound();
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+
+        let warnings_closure = Rc::clone(&warnings);
+        bus.on_max_listeners(move |_, event, count| {
+            warnings_closure
+                .borrow_mut()
+                .push(format!("{} has {} listeners", event, count));
+        })
+        .unwrap();
+        bus.set_max_listeners("render", 2).unwrap();
+
+        for _ in 0..4 {
+            bus.on("render", |_, _| {}).unwrap();
+        }
+        bus.on_any(|_, _, _| {}).unwrap();
+        assert_eq!(bus.listener_count(&"render"), 4);
+        assert_eq!(*warnings.borrow(), vec!["render has 3 listeners"]);
+
+        bus.set_strict_max_listeners(true);
+        assert_eq!(
+            bus.on("render", |_, _| {}),
+            Err(Error::TooManyListeners { limit: 2, count: 5 })
+        );
+        bus.on("resize", |_, _| {}).unwrap();
+        assert_eq!(bus.listener_count(&"render"), 4);
+    }
+
+    #[test]
+    fn on_variant() {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        enum UserEvent {
+            LoggedIn { id: u32 },
+            LoggedOut { id: u32 },
+        }
+
+        let bus: EventBus<UserEvent, ()> = EventBus::unbound();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let log_closure = Rc::clone(&log);
+        bus.on(UserEvent::LoggedIn { id: 2 }, move |_, _| {
+            log_closure
+                .borrow_mut()
+                .push((UserEvent::LoggedIn { id: 2 }, "specific"));
+        })
+        .unwrap();
+
+        let log_closure = Rc::clone(&log);
+        let subscription = bus
+            .on_variant(UserEvent::LoggedIn { id: 0 }, move |_, event, _| {
+                log_closure.borrow_mut().push((*event, "variant"));
+            })
+            .unwrap();
+
+        bus.emit(UserEvent::LoggedIn { id: 1 })
+            .expect("Failed to emit");
+        bus.emit(UserEvent::LoggedIn { id: 2 })
+            .expect("Failed to emit");
+        bus.emit(UserEvent::LoggedOut { id: 1 })
+            .expect("Failed to emit");
+        assert!(bus.off(subscription).unwrap());
+        bus.emit(UserEvent::LoggedIn { id: 3 })
+            .expect("Failed to emit");
+
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                (UserEvent::LoggedIn { id: 1 }, "variant"),
+                (UserEvent::LoggedIn { id: 2 }, "specific"),
+                (UserEvent::LoggedIn { id: 2 }, "variant"),
+            ]
+        );
+    }
+
+    #[test]
+    fn topics() {
+        let bus: TopicBus<u8> = TopicBus::new();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let mut subscriptions = Vec::new();
+        for pattern in ["order.*", "order.#", "#.shipped", "invoice.#"] {
+            let log_closure = Rc::clone(&log);
+            let subscription = bus
+                .on(pattern, move |_, topic, value| {
+                    log_closure
+                        .borrow_mut()
+                        .push((pattern, topic.to_owned(), value.copied()));
+                })
+                .unwrap();
+            subscriptions.push(subscription);
+        }
+
+        bus.emit_with_value("order.shipped", Some(&1))
+            .expect("Failed to emit");
+        assert!(bus.off(subscriptions[1]).unwrap());
+        bus.emit("order.eu.shipped").expect("Failed to emit");
+
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                ("order.*", "order.shipped".to_owned(), Some(1)),
+                ("order.#", "order.shipped".to_owned(), Some(1)),
+                ("#.shipped", "order.shipped".to_owned(), Some(1)),
+                ("#.shipped", "order.eu.shipped".to_owned(), None),
+            ]
+        );
+        assert_eq!(bus.event_count(), 2);
+    }
+
+    #[test]
+    fn topics_re_emit() {
+        let bus: TopicBus<()> = TopicBus::new();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        bus.on("order.created", |inner_bus, _, _| {
+            inner_bus
+                .emit("order.validated")
+                .expect("Cannot emit order.validated");
+        })
+        .unwrap();
+
+        let log_closure = Rc::clone(&log);
+        bus.on("order.#", move |_, topic, _| {
+            log_closure.borrow_mut().push(topic.to_owned());
+        })
+        .unwrap();
+
+        bus.emit("order.created").expect("Failed to emit");
+
+        assert_eq!(*log.borrow(), vec!["order.validated", "order.created"]);
+    }
+
+    #[test]
+    fn invalid_topics() {
+        let bus: TopicBus<()> = TopicBus::new();
+
+        assert_eq!(
+            bus.on("order..created", |_, _, _| {}),
+            Err(Error::InvalidTopic)
+        );
+        assert_eq!(bus.emit("order.*"), Err(Error::InvalidTopic));
+        assert_eq!(bus.event_count(), 0);
+    }
+
+    #[test]
+    fn typed_events() {
+        struct Started(u8);
+        struct Stopped;
+
+        let bus = TypedBus::new();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let log_closure = Rc::clone(&log);
+        bus.on(move |_, event: &Started| {
+            log_closure
+                .borrow_mut()
+                .push(format!("started {}", event.0));
+        })
+        .unwrap();
+
+        let log_closure = Rc::clone(&log);
+        let subscription = bus
+            .on(move |_, _: &Stopped| {
+                log_closure.borrow_mut().push("stopped".to_owned());
+            })
+            .unwrap();
+
+        bus.emit(Started(1)).expect("Failed to emit");
+        bus.emit(Stopped).expect("Failed to emit");
+        bus.emit("unhandled").expect("Failed to emit");
+        assert!(bus.off(subscription).unwrap());
+        assert!(!bus.off(subscription).unwrap());
+        bus.emit(Stopped).expect("Failed to emit");
+
+        assert_eq!(*log.borrow(), vec!["started 1", "stopped"]);
+        assert_eq!(bus.event_count(), 4);
+    }
+
+    #[test]
+    fn typed_events_re_emit() {
+        struct Request(u8);
+        struct Response(u8);
+
+        let bus = TypedBus::new();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        bus.on(|inner_bus, request: &Request| {
+            inner_bus
+                .emit(Response(request.0 * 2))
+                .expect("Cannot emit a response");
+        })
+        .unwrap();
+
+        let log_closure = Rc::clone(&log);
+        bus.on(move |_, response: &Response| {
+            log_closure.borrow_mut().push(response.0);
+        })
+        .unwrap();
+
+        bus.emit(Request(21)).expect("Failed to emit");
+
+        assert_eq!(*log.borrow(), vec![42]);
+    }
 }
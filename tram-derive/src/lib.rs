@@ -0,0 +1,247 @@
+//! Derive macros for [tram](https://docs.rs/tram).
+//!
+//! These are re-exported by `tram::prelude` when its `derive` feature is
+//! enabled, there should be no need to depend on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Ident, Type};
+
+/// Derives `tram::prelude::Event` for an enum whose variants either carry
+/// no data or a single unnamed field, their payload.
+///
+/// For an enum named `AppEvent` this generates:
+///
+/// - `AppEventKind`, a field-less copy of the enum used to key the bus
+/// - an `app_event` module holding a unit struct for each variant,
+///   implementing `tram::prelude::Variant` to register typed listeners with
+///   `on_event`
+///
+/// Variants without data have a payload of `()`. The module of an enum
+/// whose name is a keyword once in snake case, like `Type`, is a raw
+/// identifier (`r#type`).
+///
+/// # Example
+///
+/// ```rust
+/// use tram::{prelude::*, unsync::EventBus};
+/// use std::{rc::Rc, cell::RefCell};
+///
+/// #[derive(Event)]
+/// enum AppEvent {
+///     Started,
+///     LoggedIn(String),
+/// }
+///
+/// fn main() {
+///     let bus: EventBus<AppEventKind, AppEvent> = EventBus::unbound();
+///     let users = Rc::new(RefCell::new(Vec::new()));
+///     let users_closure = Rc::clone(&users);
+///
+///     bus.on_event(app_event::LoggedIn, move |_bus, name| {
+///         users_closure.borrow_mut().push(name.clone());
+///     })
+///     .unwrap();
+///
+///     bus.emit_event(AppEvent::Started).expect("Failed to emit");
+///     bus.emit_event(AppEvent::LoggedIn("ada".to_owned())).expect("Failed to emit");
+///
+///     assert_eq!(*users.borrow(), vec!["ada"]);
+/// }
+/// ```
+#[proc_macro_derive(Event)]
+pub fn derive_event(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+const UNSUPPORTED_FIELDS: &str =
+    "`Event` variants must carry either no data or a single unnamed field";
+
+/// Keywords of any edition, which modules can only be named after as raw
+/// identifiers
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+/// Keywords that can't be raw identifiers either
+const RESERVED: &[&str] = &["crate", "self", "super"];
+
+struct EventVariant {
+    ident: Ident,
+    payload: Option<Type>,
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "`Event` can only be derived for enums",
+            ))
+        }
+    };
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "`Event` can't be derived for generic enums",
+        ));
+    }
+
+    if data.variants.is_empty() {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "`Event` can't be derived for enums without variants",
+        ));
+    }
+
+    let variants = data
+        .variants
+        .iter()
+        .map(|variant| {
+            let payload = match &variant.fields {
+                Fields::Unit => None,
+                Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                    Some(fields.unnamed[0].ty.clone())
+                }
+                _ => return Err(Error::new_spanned(variant, UNSUPPORTED_FIELDS)),
+            };
+
+            Ok(EventVariant {
+                ident: variant.ident.clone(),
+                payload,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let vis = &input.vis;
+    let event = &input.ident;
+    let kind = format_ident!("{}Kind", event);
+    let module = module_ident(event)?;
+    let idents = variants.iter().map(|v| &v.ident).collect::<Vec<_>>();
+
+    let kind_arms = variants.iter().map(|v| {
+        let ident = &v.ident;
+        match v.payload {
+            Some(_) => quote! { #event::#ident(..) => #kind::#ident },
+            None => quote! { #event::#ident => #kind::#ident },
+        }
+    });
+
+    let markers = variants.iter().map(|v| {
+        let ident = &v.ident;
+        let doc = format!("The `{}` variant of `{}`", ident, event);
+        quote! {
+            #[doc = #doc]
+            #[derive(Debug, Clone, Copy, Default)]
+            pub struct #ident;
+        }
+    });
+
+    // The impls live next to the enum rather than in the module of the
+    // markers, so that payload types resolve as written even when they
+    // share the name of their variant
+    let impls = variants.iter().map(|v| {
+        let ident = &v.ident;
+        let (payload, pattern, value) = match &v.payload {
+            Some(ty) => (
+                quote! { #ty },
+                quote! { #event::#ident(payload) },
+                quote! { payload },
+            ),
+            None => (quote! { () }, quote! { #event::#ident }, quote! { &() }),
+        };
+
+        quote! {
+            impl ::tram::prelude::Variant<#event> for #module::#ident {
+                type Payload = #payload;
+
+                fn kind() -> #kind {
+                    #kind::#ident
+                }
+
+                #[allow(unreachable_patterns)]
+                fn payload(event: &#event) -> ::std::option::Option<&Self::Payload> {
+                    match event {
+                        #pattern => ::std::option::Option::Some(#value),
+                        _ => ::std::option::Option::None,
+                    }
+                }
+            }
+        }
+    });
+
+    let kind_doc = format!("The kinds of `{}` events", event);
+    let module_doc = format!("The variants of `{}` events", event);
+
+    Ok(quote! {
+        #[doc = #kind_doc]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #vis enum #kind {
+            #(#idents,)*
+        }
+
+        impl ::tram::prelude::Event for #event {
+            type Kind = #kind;
+
+            fn kind(&self) -> #kind {
+                match self {
+                    #(#kind_arms,)*
+                }
+            }
+        }
+
+        #[doc = #module_doc]
+        #vis mod #module {
+            #(#markers)*
+        }
+
+        #(#impls)*
+    })
+}
+
+/// The name of the module holding the variants of `event`
+fn module_ident(event: &Ident) -> Result<Ident, Error> {
+    let name = snake_case(&event.to_string());
+    if RESERVED.contains(&name.as_str()) {
+        Err(Error::new_spanned(
+            event,
+            format!("`Event` can't be derived for an enum named `{}`", event),
+        ))
+    } else if KEYWORDS.contains(&name.as_str()) {
+        Ok(Ident::new_raw(&name, Span::call_site()))
+    } else {
+        Ok(Ident::new(&name, Span::call_site()))
+    }
+}
+
+/// Converts a type name like `AppEvent` to `app_event`, keeping runs of
+/// capitals together so that `HTTPEvent` becomes `http_event`
+fn snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            let after_lower = i > 0 && !chars[i - 1].is_uppercase() && chars[i - 1] != '_';
+            let ends_run = i > 0
+                && chars[i - 1].is_uppercase()
+                && chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if after_lower || ends_run {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
@@ -0,0 +1,164 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+use tram::prelude::*;
+
+#[derive(Debug, PartialEq)]
+struct User {
+    name: String,
+}
+
+#[derive(Event)]
+enum AppEvent {
+    Started,
+    LoggedIn(User),
+    Resized((u32, u32)),
+}
+
+#[derive(Debug, PartialEq)]
+struct Request {
+    path: String,
+}
+
+/// Payloads named after their variant, in an enum whose name starts with
+/// an acronym
+#[derive(Event)]
+enum HTTPEvent {
+    Request(Request),
+    Closed,
+}
+
+/// An enum whose module is named after a keyword
+#[derive(Event)]
+enum Type {
+    Int(i64),
+    Unit,
+}
+
+#[test]
+fn kinds() {
+    assert_eq!(AppEvent::Started.kind(), AppEventKind::Started);
+    assert_eq!(
+        AppEvent::Resized((1, 2)).kind(),
+        <app_event::Resized as Variant<AppEvent>>::kind()
+    );
+    assert_eq!(
+        <app_event::Resized as Variant<AppEvent>>::payload(&AppEvent::Resized((1, 2))),
+        Some(&(1, 2))
+    );
+    assert_eq!(
+        <app_event::Resized as Variant<AppEvent>>::payload(&AppEvent::Started),
+        None
+    );
+}
+
+#[test]
+fn unsync_bus() {
+    let bus: tram::unsync::EventBus<AppEventKind, AppEvent> = tram::unsync::EventBus::unbound();
+    let log = Rc::new(RefCell::new(Vec::new()));
+
+    let log_closure = Rc::clone(&log);
+    bus.on_event(app_event::LoggedIn, move |_, user| {
+        log_closure
+            .borrow_mut()
+            .push(format!("{} logged in", user.name));
+    })
+    .unwrap();
+
+    let log_closure = Rc::clone(&log);
+    bus.on_event(app_event::Resized, move |_, (width, height)| {
+        log_closure
+            .borrow_mut()
+            .push(format!("resized to {}x{}", width, height));
+    })
+    .unwrap();
+
+    let log_closure = Rc::clone(&log);
+    bus.on_event(app_event::Started, move |bus, ()| {
+        log_closure.borrow_mut().push("started".to_owned());
+        bus.emit_event(AppEvent::Resized((640, 480)))
+            .expect("Failed to emit");
+    })
+    .unwrap();
+
+    bus.emit_event(AppEvent::Started).expect("Failed to emit");
+    bus.emit_event(AppEvent::LoggedIn(User {
+        name: "ada".to_owned(),
+    }))
+    .expect("Failed to emit");
+
+    assert_eq!(
+        *log.borrow(),
+        vec!["started", "resized to 640x480", "ada logged in"]
+    );
+}
+
+#[test]
+fn sync_bus() {
+    let bus: tram::sync::EventBus<AppEventKind, AppEvent> = tram::sync::EventBus::unbound();
+    let log = Arc::new(Mutex::new(Vec::new()));
+
+    let log_closure = Arc::clone(&log);
+    bus.on_event(app_event::LoggedIn, move |_, user| {
+        log_closure.lock().unwrap().push(user.name.clone());
+    })
+    .unwrap();
+
+    let emitter = bus.clone();
+    std::thread::spawn(move || {
+        emitter
+            .emit_event(AppEvent::LoggedIn(User {
+                name: "ada".to_owned(),
+            }))
+            .expect("Failed to emit");
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(*log.lock().unwrap(), vec!["ada"]);
+}
+
+#[test]
+fn payloads_named_after_their_variant() {
+    let bus: tram::unsync::EventBus<HTTPEventKind, HTTPEvent> = tram::unsync::EventBus::unbound();
+    let paths = Rc::new(RefCell::new(Vec::new()));
+
+    let paths_closure = Rc::clone(&paths);
+    bus.on_event(http_event::Request, move |_, request: &Request| {
+        paths_closure.borrow_mut().push(request.path.clone());
+    })
+    .unwrap();
+
+    bus.emit_event(HTTPEvent::Request(Request {
+        path: "/".to_owned(),
+    }))
+    .expect("Failed to emit");
+    bus.emit_event(HTTPEvent::Closed).expect("Failed to emit");
+
+    assert_eq!(*paths.borrow(), vec!["/"]);
+    assert_eq!(
+        <http_event::Closed as Variant<HTTPEvent>>::kind(),
+        HTTPEventKind::Closed
+    );
+}
+
+#[test]
+fn keyword_modules() {
+    let bus: tram::unsync::EventBus<TypeKind, Type> = tram::unsync::EventBus::unbound();
+    let ints = Rc::new(RefCell::new(Vec::new()));
+
+    let ints_closure = Rc::clone(&ints);
+    bus.on_event(r#type::Int, move |_, int| {
+        ints_closure.borrow_mut().push(*int);
+    })
+    .unwrap();
+
+    bus.emit_event(Type::Int(42)).expect("Failed to emit");
+    bus.emit_event(Type::Unit).expect("Failed to emit");
+
+    assert_eq!(*ints.borrow(), vec![42]);
+    assert_eq!(<r#type::Unit as Variant<Type>>::kind(), TypeKind::Unit);
+}